@@ -0,0 +1,121 @@
+//! Compares route lookups in the radix tree against the `HashMap` + `RwLock`
+//! table the router used before, with static routes looked up directly and
+//! templated routes scanned in registration order.
+
+use std::collections::HashMap;
+use std::hint::black_box;
+use std::sync::RwLock;
+
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+
+#[allow(dead_code)]
+#[path = "../src/matcher.rs"]
+mod matcher;
+
+use matcher::Matcher;
+
+/// The previous routing table: exact paths in a locked map, with a fallback
+/// scan over templated routes split into segments.
+#[derive(Default)]
+struct LegacyTable {
+    exact: RwLock<HashMap<String, usize>>,
+    templated: RwLock<Vec<(Vec<String>, usize)>>,
+}
+
+impl LegacyTable {
+    fn insert(&self, route: &str, value: usize) {
+        if route.contains('{') {
+            let segments = route[1..].split('/').map(str::to_string).collect();
+            self.templated.write().unwrap().push((segments, value));
+        } else {
+            self.exact.write().unwrap().insert(route.to_string(), value);
+        }
+    }
+
+    fn at(&self, path: &str) -> Option<(usize, Vec<(String, String)>)> {
+        if let Some(value) = self.exact.read().unwrap().get(path) {
+            return Some((*value, Vec::new()));
+        }
+
+        let templated = self.templated.read().unwrap();
+        let segments: Vec<&str> = path[1..].split('/').collect();
+
+        'routes: for (pattern, value) in templated.iter() {
+            if pattern.len() != segments.len() {
+                continue;
+            }
+
+            let mut params = Vec::new();
+            for (expected, actual) in pattern.iter().zip(&segments) {
+                match expected.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+                    Some(name) => params.push((name.to_string(), actual.to_string())),
+                    None if expected == actual => {}
+                    None => continue 'routes,
+                }
+            }
+
+            return Some((*value, params));
+        }
+
+        None
+    }
+}
+
+/// Generates `count` routes spread over resources, half static and half with
+/// one or two parameters, plus the concrete paths used to look them up.
+fn routes(count: usize) -> Vec<(String, String)> {
+    (0..count)
+        .map(|i| {
+            let resource = format!("resource{}", i / 4);
+            match i % 4 {
+                0 => (format!("/{resource}"), format!("/{resource}")),
+                1 => (format!("/{resource}/list"), format!("/{resource}/list")),
+                2 => (format!("/{resource}/{{id}}"), format!("/{resource}/42")),
+                _ => (
+                    format!("/{resource}/{{id}}/items/{{item}}"),
+                    format!("/{resource}/42/items/7"),
+                ),
+            }
+        })
+        .collect()
+}
+
+fn lookups(c: &mut Criterion) {
+    let mut group = c.benchmark_group("lookup");
+
+    for count in [100, 1_000, 5_000] {
+        let routes = routes(count);
+        let mut tree = Matcher::default();
+        let legacy = LegacyTable::default();
+
+        for (id, (route, _)) in routes.iter().enumerate() {
+            tree.insert(route, id).unwrap();
+            legacy.insert(route, id);
+        }
+
+        // Look up every route, so templated routes registered late pay the
+        // full cost of the legacy scan.
+        let paths: Vec<&str> = routes.iter().map(|(_, path)| path.as_str()).collect();
+
+        group.bench_with_input(BenchmarkId::new("radix_tree", count), &paths, |b, paths| {
+            b.iter(|| {
+                for path in paths {
+                    black_box(tree.at(black_box(path)).map(|m| *m.value));
+                }
+            })
+        });
+
+        group.bench_with_input(BenchmarkId::new("legacy_map", count), &paths, |b, paths| {
+            b.iter(|| {
+                for path in paths {
+                    black_box(legacy.at(black_box(path)).map(|(value, _)| value));
+                }
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, lookups);
+criterion_main!(benches);
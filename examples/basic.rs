@@ -1,20 +1,23 @@
 use hyper::StatusCode;
 use mini_axum::{
     Router, Service,
-    extractor::State,
+    extractor::{Path, State},
+    method_router::{get, post},
     middleware::LogLayer,
     response::{IntoMiniResponse, Json},
 };
 use serde_json::{Value, json};
 use tokio::net::TcpListener;
-use tower_http::trace::TraceLayer;
 
 #[tokio::main]
 async fn main() {
+    tracing_subscriber::fmt().init();
+
     let rtr = Router::with_state("Hello world!")
-        .route("/", hello_world)
-        .route("/echo", echo_message)
-        .layer(LogLayer);
+        .route("/", get(hello_world))
+        .route("/echo", post(echo_message))
+        .route("/users/{id}", get(get_user))
+        .layer(LogLayer::new());
 
     let tcp = TcpListener::bind("127.0.0.1:9999")
         .await
@@ -34,3 +37,10 @@ pub async fn hello_world() -> impl IntoMiniResponse {
 pub async fn echo_message(Json(json): Json<Value>) -> Result<impl IntoMiniResponse, &'static str> {
     Ok(Json(json))
 }
+
+pub async fn get_user(
+    State(greeting): State<&'static str>,
+    Path(id): Path<u32>,
+) -> impl IntoMiniResponse {
+    Json(json!({"id": id, "greeting": greeting}))
+}
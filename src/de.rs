@@ -0,0 +1,544 @@
+use std::fmt;
+
+use serde::de::value::{MapDeserializer, SeqDeserializer};
+use serde::de::{self, DeserializeSeed, EnumAccess, IntoDeserializer, VariantAccess, Visitor};
+use serde::forward_to_deserialize_any;
+
+/// An error raised while deserializing captured path parameters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathDeserializationError {
+    /// The route captured a different number of parameters than the target type expects.
+    WrongNumberOfParameters { got: usize, expected: usize },
+    /// A parameter could not be parsed into the requested type.
+    ParseError {
+        key: String,
+        value: String,
+        expected_type: &'static str,
+    },
+    /// The target type requested something a path can't represent.
+    UnsupportedType { name: &'static str },
+    /// Any other error raised by the `Deserialize` implementation.
+    Message(String),
+}
+
+impl fmt::Display for PathDeserializationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WrongNumberOfParameters { got, expected } => write!(
+                f,
+                "Wrong number of path arguments. Expected {expected} but got {got}"
+            ),
+            Self::ParseError {
+                key,
+                value,
+                expected_type,
+            } => write!(
+                f,
+                "Cannot parse `{key}` with value `{value}` to a `{expected_type}`"
+            ),
+            Self::UnsupportedType { name } => {
+                write!(f, "Unsupported type `{name}` for path parameters")
+            }
+            Self::Message(msg) => f.write_str(msg),
+        }
+    }
+}
+
+impl std::error::Error for PathDeserializationError {}
+
+impl de::Error for PathDeserializationError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self::Message(msg.to_string())
+    }
+}
+
+/// Deserializes the full set of `(name, value)` pairs captured by a route.
+pub(crate) struct PathDeserializer<'de> {
+    params: &'de [(String, String)],
+}
+
+impl<'de> PathDeserializer<'de> {
+    pub(crate) fn new(params: &'de [(String, String)]) -> Self {
+        Self { params }
+    }
+
+    fn single(&self) -> Result<ValueDeserializer<'de>, PathDeserializationError> {
+        match self.params {
+            [(key, value)] => Ok(ValueDeserializer { key, value }),
+            _ => Err(PathDeserializationError::WrongNumberOfParameters {
+                got: self.params.len(),
+                expected: 1,
+            }),
+        }
+    }
+
+    fn values(&self) -> impl Iterator<Item = ValueDeserializer<'de>> {
+        self.params
+            .iter()
+            .map(|(key, value)| ValueDeserializer { key, value })
+    }
+
+    fn entries(&self) -> impl Iterator<Item = (&'de str, ValueDeserializer<'de>)> {
+        self.params
+            .iter()
+            .map(|(key, value)| (key.as_str(), ValueDeserializer { key, value }))
+    }
+
+    fn check_len(&self, expected: usize) -> Result<(), PathDeserializationError> {
+        if self.params.len() == expected {
+            Ok(())
+        } else {
+            Err(PathDeserializationError::WrongNumberOfParameters {
+                got: self.params.len(),
+                expected,
+            })
+        }
+    }
+}
+
+macro_rules! forward_to_single {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+                self.single()?.$method(visitor)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for PathDeserializer<'de> {
+    type Error = PathDeserializationError;
+
+    forward_to_single! {
+        deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_i128 deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64
+        deserialize_u128 deserialize_f32 deserialize_f64 deserialize_char deserialize_str
+        deserialize_string deserialize_bytes deserialize_byte_buf
+    }
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_seq(SeqDeserializer::new(self.values()))
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.check_len(len)?;
+        visitor.visit_seq(SeqDeserializer::new(self.values()))
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_map(MapDeserializer::new(self.entries()))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.single()?.deserialize_enum(name, variants, visitor)
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.single()?.deserialize_identifier(visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+}
+
+/// Deserializes a single captured parameter, parsing primitives from its text.
+#[derive(Clone, Copy)]
+struct ValueDeserializer<'de> {
+    key: &'de str,
+    value: &'de str,
+}
+
+impl ValueDeserializer<'_> {
+    fn parse_error(&self, expected_type: &'static str) -> PathDeserializationError {
+        PathDeserializationError::ParseError {
+            key: self.key.to_string(),
+            value: self.value.to_string(),
+            expected_type,
+        }
+    }
+}
+
+macro_rules! parse_value {
+    ($($method:ident => $visit:ident: $ty:ty),* $(,)?) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+                let value: $ty = self
+                    .value
+                    .parse()
+                    .map_err(|_| self.parse_error(stringify!($ty)))?;
+                visitor.$visit(value)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for ValueDeserializer<'de> {
+    type Error = PathDeserializationError;
+
+    parse_value! {
+        deserialize_bool => visit_bool: bool,
+        deserialize_i8 => visit_i8: i8,
+        deserialize_i16 => visit_i16: i16,
+        deserialize_i32 => visit_i32: i32,
+        deserialize_i64 => visit_i64: i64,
+        deserialize_i128 => visit_i128: i128,
+        deserialize_u8 => visit_u8: u8,
+        deserialize_u16 => visit_u16: u16,
+        deserialize_u32 => visit_u32: u32,
+        deserialize_u64 => visit_u64: u64,
+        deserialize_u128 => visit_u128: u128,
+        deserialize_f32 => visit_f32: f32,
+        deserialize_f64 => visit_f64: f64,
+        deserialize_char => visit_char: char,
+    }
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_borrowed_str(self.value)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_enum(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+        Err(PathDeserializationError::UnsupportedType { name: "sequence" })
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+        Err(PathDeserializationError::UnsupportedType { name: "map" })
+    }
+
+    forward_to_deserialize_any! {
+        str string bytes byte_buf unit unit_struct tuple tuple_struct struct
+        identifier ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, PathDeserializationError> for ValueDeserializer<'de> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+impl<'de> EnumAccess<'de> for ValueDeserializer<'de> {
+    type Error = PathDeserializationError;
+    type Variant = UnitOnly;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self::Variant), Self::Error> {
+        let variant = seed.deserialize(self.value.into_deserializer())?;
+        Ok((variant, UnitOnly))
+    }
+}
+
+/// Only unit variants can be represented by a single path segment.
+struct UnitOnly;
+
+impl<'de> VariantAccess<'de> for UnitOnly {
+    type Error = PathDeserializationError;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        _seed: T,
+    ) -> Result<T::Value, Self::Error> {
+        Err(PathDeserializationError::UnsupportedType {
+            name: "newtype enum variant",
+        })
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        Err(PathDeserializationError::UnsupportedType {
+            name: "tuple enum variant",
+        })
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        Err(PathDeserializationError::UnsupportedType {
+            name: "struct enum variant",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde::Deserialize;
+
+    use super::*;
+
+    fn params(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn from_params<'de, T: Deserialize<'de>>(
+        params: &'de [(String, String)],
+    ) -> Result<T, PathDeserializationError> {
+        T::deserialize(PathDeserializer::new(params))
+    }
+
+    #[test]
+    fn parses_a_single_primitive() {
+        assert_eq!(from_params::<u32>(&params(&[("id", "42")])), Ok(42));
+        assert_eq!(from_params::<bool>(&params(&[("flag", "true")])), Ok(true));
+        assert_eq!(from_params::<char>(&params(&[("c", "x")])), Ok('x'));
+        assert_eq!(
+            from_params::<String>(&params(&[("name", "ferris")])),
+            Ok("ferris".to_string())
+        );
+    }
+
+    #[test]
+    fn borrows_strings_from_the_params() {
+        let params = params(&[("name", "ferris")]);
+        assert_eq!(from_params::<&str>(&params), Ok("ferris"));
+    }
+
+    #[test]
+    fn reports_the_parameter_that_failed_to_parse() {
+        assert_eq!(
+            from_params::<u8>(&params(&[("id", "300")])),
+            Err(PathDeserializationError::ParseError {
+                key: "id".to_string(),
+                value: "300".to_string(),
+                expected_type: "u8",
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_the_wrong_number_of_parameters() {
+        assert_eq!(
+            from_params::<u32>(&params(&[("a", "1"), ("b", "2")])),
+            Err(PathDeserializationError::WrongNumberOfParameters {
+                got: 2,
+                expected: 1,
+            })
+        );
+        assert_eq!(
+            from_params::<(u32, u32, u32)>(&params(&[("a", "1"), ("b", "2")])),
+            Err(PathDeserializationError::WrongNumberOfParameters {
+                got: 2,
+                expected: 3,
+            })
+        );
+    }
+
+    #[test]
+    fn parses_tuples_in_route_order() {
+        let params = params(&[("user", "7"), ("post", "hello")]);
+        assert_eq!(
+            from_params::<(u32, String)>(&params),
+            Ok((7, "hello".to_string()))
+        );
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Ids(u32, String);
+        assert_eq!(from_params::<Ids>(&params), Ok(Ids(7, "hello".to_string())));
+    }
+
+    #[test]
+    fn parses_structs_and_maps_by_name() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Params {
+            post: String,
+            user: u32,
+        }
+
+        let params = params(&[("user", "7"), ("post", "hello")]);
+        assert_eq!(
+            from_params::<Params>(&params),
+            Ok(Params {
+                post: "hello".to_string(),
+                user: 7,
+            })
+        );
+
+        let map = from_params::<HashMap<String, String>>(&params).unwrap();
+        assert_eq!(map["user"], "7");
+        assert_eq!(map["post"], "hello");
+    }
+
+    #[test]
+    fn parses_nested_values_inside_structs() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        #[serde(rename_all = "lowercase")]
+        enum Kind {
+            Post,
+            Comment,
+        }
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Params {
+            kind: Kind,
+            id: Option<u32>,
+        }
+
+        assert_eq!(
+            from_params::<Params>(&params(&[("kind", "comment"), ("id", "3")])),
+            Ok(Params {
+                kind: Kind::Comment,
+                id: Some(3),
+            })
+        );
+        assert!(matches!(
+            from_params::<Params>(&params(&[("kind", "post"), ("id", "x")])),
+            Err(PathDeserializationError::ParseError { .. })
+        ));
+        assert_eq!(
+            from_params::<Kind>(&params(&[("kind", "post")])),
+            Ok(Kind::Post)
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_enum_variants() {
+        #[derive(Debug, Deserialize)]
+        enum Kind {
+            #[allow(dead_code)]
+            Post,
+        }
+
+        assert!(matches!(
+            from_params::<Kind>(&params(&[("kind", "page")])),
+            Err(PathDeserializationError::Message(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_enum_variants_with_data() {
+        #[derive(Debug, Deserialize)]
+        enum Kind {
+            #[allow(dead_code)]
+            Post(u32),
+        }
+
+        assert_eq!(
+            from_params::<Kind>(&params(&[("kind", "Post")])).unwrap_err(),
+            PathDeserializationError::UnsupportedType {
+                name: "newtype enum variant"
+            }
+        );
+    }
+
+    #[test]
+    fn parses_options_and_newtypes() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct UserId(u32);
+
+        assert_eq!(
+            from_params::<Option<u32>>(&params(&[("id", "5")])),
+            Ok(Some(5))
+        );
+        assert_eq!(
+            from_params::<UserId>(&params(&[("id", "5")])),
+            Ok(UserId(5))
+        );
+    }
+
+    #[test]
+    fn rejects_sequences_inside_a_parameter() {
+        #[derive(Debug, Deserialize)]
+        struct Params {
+            #[allow(dead_code)]
+            ids: Vec<u32>,
+        }
+
+        assert_eq!(
+            from_params::<Params>(&params(&[("ids", "1,2")])).unwrap_err(),
+            PathDeserializationError::UnsupportedType { name: "sequence" }
+        );
+    }
+}
@@ -1,305 +1,248 @@
-use std::any::Any;
 use std::marker::PhantomData;
 use std::sync::Arc;
+use std::task::{Context, Poll};
 
-use bytes::Bytes;
-use futures::TryFutureExt;
 use futures::future::BoxFuture;
-use http_body_util::Full;
 use hyper::body::Incoming;
-// use hyper::service::Service;
-use hyper::{Request, Response};
-use tower::{Layer, Service, ServiceBuilder};
+use hyper::{Request, Response, StatusCode};
+use tower::util::BoxCloneSyncService;
+use tower::{Layer, ServiceExt};
 
+use crate::body::{Body, BoxError};
 use crate::extractor::{FromRequest, FromRequestParts};
-use crate::response::IntoMiniResponse;
+use crate::response::{IntoMiniResponse, MiniResponse};
+use crate::router::DynService;
+
+/// Something that can respond to requests routed to it, given the router's
+/// state.
+///
+/// It's implemented for async functions taking up to 16 extractors, where
+/// every argument but the last implements
+/// [`FromRequestParts`](crate::extractor::FromRequestParts) and the last one
+/// implements [`FromRequest`](crate::extractor::FromRequest). `T` is the tuple
+/// of those arguments, letting each arity have its own impl.
+///
+/// Other types can implement it directly:
+///
+/// ```no_run
+/// # use futures::future::BoxFuture;
+/// # use hyper::{Request, body::Incoming};
+/// # use mini_axum::{Router, endpoint::Handler, method_router::get};
+/// # use mini_axum::response::{IntoMiniResponse, MiniResponse};
+/// struct Static(&'static str);
+///
+/// impl<S> Handler<(), S> for Static {
+///     fn call(&self, _req: Request<Incoming>, _state: S) -> BoxFuture<'static, MiniResponse> {
+///         let body = self.0;
+///         Box::pin(async move { body.into_response() })
+///     }
+/// }
+///
+/// # let router = Router::stateless();
+/// router.route("/", get(Static("Hello world!")));
+/// ```
+///
+/// The trait is object safe, so handlers can also be stored as
+/// `Box<dyn Handler<T, S>>`.
+pub trait Handler<T, S>: Send + Sync + 'static {
+    fn call(&self, req: Request<Incoming>, state: S) -> BoxFuture<'static, MiniResponse>;
+
+    /// Binds `state` to the handler, turning it into a `tower::Service`.
+    fn with_state(self, state: S) -> HandlerService<Self, T, S>
+    where
+        Self: Sized,
+        S: Clone + Send + Sync + 'static,
+    {
+        HandlerService::new(Arc::new(self), state)
+    }
 
-pub trait IntoHandler<T, S>: Sized + Clone {
-    fn into_handler(self, state: S) -> IntoHandlerStruct<Self, T, S>;
-}
+    /// Builds the service serving the handler once it's bound to `state`.
+    #[doc(hidden)]
+    fn into_service(self: Arc<Self>, state: S) -> DynService
+    where
+        Self: Sized,
+        S: Clone + Send + Sync + 'static,
+    {
+        BoxCloneSyncService::new(tower::service_fn(move |req| {
+            let res = self.call(req, state.clone());
+            async move { Ok(res.await.hyper_response()) }
+        }))
+    }
 
-impl<F, Fut, I, S> IntoHandler<(), S> for F
-where
-    F: Fn() -> Fut + Clone + Send + Sync + 'static,
-    Fut: Future<Output = I> + Send + 'static,
-    I: IntoMiniResponse,
-{
-    fn into_handler(self, state: S) -> IntoHandlerStruct<Self, (), S> {
-        IntoHandlerStruct {
-            inner: self,
-            state,
-            _tytypes: PhantomData,
+    /// Wraps the handler with `layer`, so middleware can apply to a single
+    /// handler instead of a whole router:
+    ///
+    /// ```no_run
+    /// # use hyper::{HeaderMap, Request, StatusCode, body::Incoming};
+    /// # use mini_axum::{Router, endpoint::Handler, method_router::get};
+    /// # use mini_axum::middleware::{Next, from_fn};
+    /// # use mini_axum::response::{IntoMiniResponse, MiniResponse};
+    /// # async fn admin() -> &'static str { "admin" }
+    /// # async fn require_auth(headers: HeaderMap, req: Request<Incoming>, next: Next) -> MiniResponse {
+    /// #     match headers.get("authorization") {
+    /// #         Some(_) => next.run(req).await.into_response(),
+    /// #         None => StatusCode::UNAUTHORIZED.into_response(),
+    /// #     }
+    /// # }
+    /// # let router = Router::stateless();
+    /// router.route("/admin", get(admin.layer(from_fn(require_auth))));
+    /// ```
+    ///
+    /// Errors returned by the layer are turned into
+    /// `500 Internal Server Error` responses.
+    fn layer<L>(self, layer: L) -> Layered<Self, T, S, L>
+    where
+        Self: Sized,
+    {
+        Layered {
+            handler: Arc::new(self),
+            layer,
+            _marker: PhantomData,
         }
     }
 }
 
-impl<F, Fut, I, S, T1> IntoHandler<(T1,), S> for F
+impl<F, Fut, I, S> Handler<(), S> for F
 where
-    F: Fn(T1) -> Fut + Clone + Send + Sync + 'static,
+    F: Fn() -> Fut + Clone + Send + Sync + 'static,
     Fut: Future<Output = I> + Send + 'static,
     I: IntoMiniResponse,
-    T1: FromRequest<S> + Send + 'static,
 {
-    fn into_handler(self, state: S) -> IntoHandlerStruct<Self, (T1,), S> {
-        IntoHandlerStruct {
-            inner: self,
-            state,
-            _tytypes: PhantomData,
-        }
+    fn call(&self, _req: Request<Incoming>, _state: S) -> BoxFuture<'static, MiniResponse> {
+        let handler = self.clone();
+
+        Box::pin(async move { handler().await.into_response() })
     }
 }
 
-impl<F, Fut, I, S, T1, T2> IntoHandler<(T1, T2), S> for F
-where
-    F: Fn(T1, T2) -> Fut + Clone + Send + Sync + 'static,
-    Fut: Future<Output = I> + Send + 'static,
-    I: IntoMiniResponse,
-    T1: FromRequestParts<S> + Send + 'static,
-    T2: FromRequest<S> + Send + 'static,
-{
-    fn into_handler(self, state: S) -> IntoHandlerStruct<Self, (T1, T2), S> {
-        IntoHandlerStruct {
-            inner: self,
-            state,
-            _tytypes: PhantomData,
+macro_rules! impl_handler {
+    ([$($ty:ident),*], $last:ident) => {
+        impl<F, Fut, I, S, $($ty,)* $last> Handler<($($ty,)* $last,), S> for F
+        where
+            F: Fn($($ty,)* $last) -> Fut + Clone + Send + Sync + 'static,
+            Fut: Future<Output = I> + Send + 'static,
+            I: IntoMiniResponse,
+            S: Clone + Send + Sync + 'static,
+            $($ty: FromRequestParts<S> + Send + 'static,)*
+            $last: FromRequest<S> + Send + 'static,
+        {
+            #[allow(non_snake_case)]
+            fn call(&self, req: Request<Incoming>, state: S) -> BoxFuture<'static, MiniResponse> {
+                let handler = self.clone();
+
+                Box::pin(async move {
+                    let ($($ty,)* $last,) =
+                        match <($($ty,)* $last,)>::from_request(req, &state).await {
+                            Ok(args) => args,
+                            Err(rejection) => return rejection.into_response(),
+                        };
+
+                    handler($($ty,)* $last).await.into_response()
+                })
+            }
         }
-    }
+    };
 }
 
-pub struct IntoHandlerStruct<H, T, S> {
-    inner: H,
-    state: S,
-    _tytypes: PhantomData<T>,
+all_the_tuples!(impl_handler);
+
+/// A [`Handler`] wrapped with a layer by [`Handler::layer`].
+pub struct Layered<H, T, S, L> {
+    handler: Arc<H>,
+    layer: L,
+    _marker: PhantomData<fn() -> (T, S)>,
 }
 
-impl<H, T, S> Clone for IntoHandlerStruct<H, T, S>
+impl<H, T, S, L> Handler<T, S> for Layered<H, T, S, L>
 where
-    H: Clone,
-    S: Clone,
+    H: Handler<T, S>,
+    T: 'static,
+    S: Clone + Send + Sync + 'static,
+    L: Layer<HandlerService<H, T, S>> + Send + Sync + 'static,
+    L::Service: tower::Service<Request<Incoming>, Response = Response<Body>>
+        + Clone
+        + Send
+        + Sync
+        + 'static,
+    <L::Service as tower::Service<Request<Incoming>>>::Error: Into<BoxError>,
+    <L::Service as tower::Service<Request<Incoming>>>::Future: Send + 'static,
 {
-    fn clone(&self) -> Self {
-        Self {
-            inner: self.inner.clone(),
-            state: self.state.clone(),
-            _tytypes: PhantomData,
-        }
+    /// Wraps the handler for this request only. Routers use
+    /// [`into_service`](Handler::into_service) instead, which wraps it once.
+    fn call(&self, req: Request<Incoming>, state: S) -> BoxFuture<'static, MiniResponse> {
+        let service = self
+            .layer
+            .layer(HandlerService::new(self.handler.clone(), state));
+
+        Box::pin(async move { layered_response(service.oneshot(req).await) })
+    }
+
+    fn into_service(self: Arc<Self>, state: S) -> DynService {
+        let service = self
+            .layer
+            .layer(HandlerService::new(self.handler.clone(), state));
+
+        BoxCloneSyncService::new(tower::service_fn(move |req| {
+            let service = service.clone();
+            async move { Ok(layered_response(service.oneshot(req).await).hyper_response()) }
+        }))
     }
 }
 
-impl<H, T, S> IntoHandlerStruct<H, T, S> {
-    pub fn into_service(self, state: S) -> HandlerService<H, T, S> {
-        HandlerService {
-            inner: self.inner,
-            state,
-            _tytypes: self._tytypes,
+/// Turns the result of a layered handler into a response, errors becoming
+/// `500 Internal Server Error` responses.
+fn layered_response<E>(result: Result<Response<Body>, E>) -> MiniResponse
+where
+    E: Into<BoxError>,
+{
+    match result {
+        Ok(res) => res.into_response(),
+        Err(err) => {
+            let err: BoxError = err.into();
+            (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response()
         }
     }
 }
 
+/// A [`Handler`] bound to the router's state, serving requests as a
+/// `tower::Service`.
 pub struct HandlerService<H, T, S> {
-    inner: H,
-    state: S,
-    _tytypes: PhantomData<T>,
+    service: DynService,
+    _marker: PhantomData<fn(H, S) -> T>,
 }
 
-impl<H, T, S> Clone for HandlerService<H, T, S>
+impl<H, T, S> HandlerService<H, T, S>
 where
-    H: Clone,
-    S: Clone,
+    H: Handler<T, S>,
+    S: Clone + Send + Sync + 'static,
 {
-    fn clone(&self) -> Self {
+    pub(crate) fn new(handler: Arc<H>, state: S) -> Self {
         Self {
-            inner: self.inner.clone(),
-            state: self.state.clone(),
-            _tytypes: PhantomData,
+            service: handler.into_service(state),
+            _marker: PhantomData,
         }
     }
 }
 
-impl<H, Fut, S, I> tower::Service<Request<Incoming>> for IntoHandlerStruct<H, (), S>
-where
-    H: Fn() -> Fut + Clone + Send + Sync + 'static,
-    Fut: Future<Output = I> + Send + 'static,
-    I: IntoMiniResponse,
-    S: Clone + Send + Sync + 'static,
-{
-    type Error = hyper::Error;
-    type Response = Response<Full<Bytes>>;
-    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
-    fn poll_ready(
-        &mut self,
-        cx: &mut std::task::Context<'_>,
-    ) -> std::task::Poll<Result<(), Self::Error>> {
-        std::task::Poll::Ready(Ok(()))
-    }
-    fn call(&mut self, _req: Request<Incoming>) -> Self::Future {
-        let thing = self.inner.clone();
-
-        Box::pin(async move { Ok((thing)().await.into_response().hyper_response()) })
+impl<H, T, S> Clone for HandlerService<H, T, S> {
+    fn clone(&self) -> Self {
+        Self {
+            service: self.service.clone(),
+            _marker: PhantomData,
+        }
     }
 }
 
-impl<H, Fut, S, I, T1> tower::Service<Request<Incoming>> for IntoHandlerStruct<H, (T1,), S>
-where
-    H: Fn(T1) -> Fut + Clone + Send + Sync + 'static,
-    Fut: Future<Output = I> + Send + 'static,
-    I: IntoMiniResponse,
-    T1: FromRequest<S> + Send + 'static,
-    S: Send + Clone + Sync + 'static,
-{
+impl<H, T, S> tower::Service<Request<Incoming>> for HandlerService<H, T, S> {
+    type Response = Response<Body>;
     type Error = hyper::Error;
-    type Response = Response<Full<Bytes>>;
     type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
-    fn poll_ready(
-        &mut self,
-        cx: &mut std::task::Context<'_>,
-    ) -> std::task::Poll<Result<(), Self::Error>> {
-        std::task::Poll::Ready(Ok(()))
-    }
-    fn call(&mut self, req: Request<Incoming>) -> Self::Future {
-        let thing = self.inner.clone();
-        let state = self.state.clone();
 
-        Box::pin(async move {
-            let (t1) = T1::from_request(req, &state).await;
-            Ok((thing)(t1).await.into_response().hyper_response())
-        })
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
     }
-}
 
-impl<H, Fut, S, I, T1, T2> tower::Service<Request<Incoming>> for IntoHandlerStruct<H, (T1, T2), S>
-where
-    H: Fn(T1, T2) -> Fut + Clone + Send + Sync + 'static,
-    Fut: Future<Output = I> + Send + 'static,
-    I: IntoMiniResponse,
-    T2: FromRequest<S> + Send + 'static,
-    T1: FromRequestParts<S> + Send + 'static,
-    S: Send + Clone + Sync + 'static,
-{
-    type Error = hyper::Error;
-    type Response = Response<Full<Bytes>>;
-    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
-    fn poll_ready(
-        &mut self,
-        cx: &mut std::task::Context<'_>,
-    ) -> std::task::Poll<Result<(), Self::Error>> {
-        std::task::Poll::Ready(Ok(()))
-    }
     fn call(&mut self, req: Request<Incoming>) -> Self::Future {
-        let thing = self.inner.clone();
-        let state = self.state.clone();
-
-        Box::pin(async move {
-            let (t1, t2) = <(T1, T2)>::from_request(req, &state).await;
-            Ok((thing)(t1, t2).await.into_response().hyper_response())
-        })
+        self.service.call(req)
     }
 }
-
-// impl<H, T, S> tower::Service<Request<Incoming>> for HandlerService<H, T, S>
-// where
-//     S: Clone + Send + Sync + 'static,
-//     H: Endpoint<S> + Clone + Send + Sync + 'static,
-// {
-//     type Error = hyper::Error;
-//     type Response = Response<Full<Bytes>>;
-//     type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
-//     fn poll_ready(
-//         &mut self,
-//         _cx: &mut std::task::Context<'_>,
-//     ) -> std::task::Poll<Result<(), Self::Error>> {
-//         std::task::Poll::Ready(Ok(()))
-//     }
-
-//     fn call(&mut self, req: Request<Incoming>) -> Self::Future {
-//         let service = self.inner.clone();
-//         let state = self.state.clone();
-//         Box::pin(async move { service.call_handler(req, state).await })
-//     }
-// }
-
-// impl<H, T, S> Endpoint<S> for HandlerService<H, T, S>
-// where
-//     S: Clone + Send + Sync + 'static,
-//     // H: Endpoint<S> + Clone + Send + Sync + 'static,
-//     T: Sync + Send + 'static,
-// {
-//     fn call_handler(
-//         &self,
-//         req: Request<Incoming>,
-//         state: S,
-//     ) -> BoxFuture<'static, Result<Response<Full<Bytes>>, hyper::Error>> {
-//         let service = self.inner.clone();
-//         Box::pin(async move { service.call_handler(req, state).await })
-//     }
-// }
-
-// pub trait Endpoint<S>: Any + Send + Sync {
-//     fn call_handler(
-//         &self,
-//         req: Request<Incoming>,
-//         state: S,
-//     ) -> BoxFuture<'static, Result<Response<Full<Bytes>>, hyper::Error>>;
-// }
-
-// impl<H, Fut, I, S> Endpoint<S> for IntoHandlerStruct<H, (), S>
-// where
-//     H: Fn() -> Fut + Clone + Send + Sync + 'static,
-//     Fut: Future<Output = I> + Send,
-//     I: IntoMiniResponse,
-//     S: Clone + Send + Sync + 'static,
-// {
-//     fn call_handler(
-//         &self,
-//         _req: Request<Incoming>,
-//         _state: S,
-//     ) -> BoxFuture<'static, Result<Response<Full<Bytes>>, hyper::Error>> {
-//         let inner = self.inner.clone();
-//         Box::pin(async move { Ok((inner)().await.into_response().hyper_response()) })
-//     }
-// }
-
-// impl<H, Fut, I, T1, S> Endpoint<S> for IntoHandlerStruct<H, (T1,), S>
-// where
-//     H: Fn(T1) -> Fut + Clone + Send + Sync + 'static,
-//     Fut: Future<Output = I> + Send,
-//     I: IntoMiniResponse,
-//     S: Clone + Send + Sync + 'static,
-//     T1: FromRequest<S> + Send + Sync + 'static,
-// {
-//     fn call_handler(
-//         &self,
-//         req: Request<Incoming>,
-//         state: S,
-//     ) -> BoxFuture<'static, Result<Response<Full<Bytes>>, hyper::Error>> {
-//         let inner = self.inner.clone();
-//         Box::pin(async move {
-//             let res = T1::from_request(req, &state).await;
-
-//             Ok((inner)(res).await.into_response().hyper_response())
-//         })
-//     }
-// }
-
-// impl<H, Fut, I, T1, T2, S> Endpoint<S> for IntoHandlerStruct<H, (T1, T2), S>
-// where
-//     H: Fn(T1, T2) -> Fut + Clone + Send + Sync + 'static,
-//     Fut: Future<Output = I> + Send,
-//     I: IntoMiniResponse,
-//     S: Clone + Send + Sync + 'static,
-//     T1: FromRequestParts<S> + Send + Sync + 'static,
-//     T2: FromRequest<S> + Send + Sync + 'static,
-// {
-//     fn call_handler(
-//         &self,
-//         req: Request<Incoming>,
-//         state: S,
-//     ) -> BoxFuture<'static, Result<Response<Full<Bytes>>, hyper::Error>> {
-//         let inner = self.inner.clone();
-//         Box::pin(async move {
-//             let (t1, t2) = <(T1, T2)>::from_request(req, &state).await;
-
-//             Ok((inner)(t1, t2).await.into_response().hyper_response())
-//         })
-//     }
-// }
@@ -1,62 +1,247 @@
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use http::header::{CONTENT_LENGTH, CONTENT_TYPE};
 use http::request::Parts;
-use http_body_util::BodyExt;
+use http::{HeaderMap, Method, Uri};
+use http_body_util::{BodyExt, LengthLimitError, Limited};
+use hyper::body::Bytes;
 use hyper::{Request, body::Incoming};
+use percent_encoding::percent_decode_str;
 use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::error::Category;
+use tower::{Layer, Service};
+
+use crate::de::PathDeserializer;
+use crate::matcher::Params;
+use crate::rejection::{
+    BodyRejection, ConnectInfoRejection, FormRejection, JsonRejection, MatchedPathRejection,
+    PathRejection, QueryRejection,
+};
+use crate::response::{Form, IntoMiniResponse, Json, MiniResponse};
 
-use crate::response::Json;
+/// Extracts a value from the whole request, consuming its body.
+///
+/// If extraction fails, the handler isn't called and the rejection is sent
+/// as the response instead.
+pub trait FromRequest<S>: Sized + Send + Sync {
+    type Rejection: IntoMiniResponse;
 
-pub trait FromRequest<S>: Send + Sync {
-    fn from_request(req: Request<Incoming>, state: &S) -> impl Future<Output = Self> + Send;
+    fn from_request(
+        req: Request<Incoming>,
+        state: &S,
+    ) -> impl Future<Output = Result<Self, Self::Rejection>> + Send;
 }
 
-impl<S, T> FromRequest<S> for Json<T>
-where
-    T: for<'a> Deserialize<'a> + Send + Sync,
-    S: Clone + Send + Sync,
-{
-    async fn from_request(req: Request<Incoming>, _state: &S) -> Self {
-        let (_, body) = req.into_parts();
-        let body: Vec<u8> = body.collect().await.unwrap().to_bytes().to_vec();
-        let json: T = serde_json::from_slice(&body).unwrap();
+/// The body limit applied when no [`DefaultBodyLimit`] layer is present.
+const DEFAULT_BODY_LIMIT: usize = 2 * 1024 * 1024;
+
+/// Changes the maximum size of request bodies read by extractors such as
+/// [`Json`], [`Form`] and [`Multipart`](crate::multipart::Multipart), which is
+/// 2 MiB by default.
+///
+/// Apply it to a whole router with [`Router::layer`](crate::Router::layer) or
+/// to a single route with [`MethodRouter::layer`](crate::MethodRouter::layer):
+///
+/// ```no_run
+/// # use mini_axum::{Router, extractor::DefaultBodyLimit, method_router::post};
+/// # async fn upload() -> &'static str { "" }
+/// # async fn stream() -> &'static str { "" }
+/// Router::stateless()
+///     .route("/upload", post(upload).layer(DefaultBodyLimit::max(100 * 1024 * 1024)))
+///     .route("/stream", post(stream).layer(DefaultBodyLimit::disable()));
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct DefaultBodyLimit {
+    limit: Option<usize>,
+}
 
-        Json(json)
+impl DefaultBodyLimit {
+    /// Limits request bodies to `limit` bytes.
+    pub fn max(limit: usize) -> Self {
+        Self { limit: Some(limit) }
+    }
+
+    /// Lets extractors read bodies of any size.
+    pub fn disable() -> Self {
+        Self { limit: None }
     }
 }
 
-impl<S, T1> FromRequest<S> for (T1,)
+impl<S> Layer<S> for DefaultBodyLimit {
+    type Service = DefaultBodyLimitService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        DefaultBodyLimitService {
+            inner,
+            limit: *self,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct DefaultBodyLimitService<S> {
+    inner: S,
+    limit: DefaultBodyLimit,
+}
+
+impl<S> Service<Request<Incoming>> for DefaultBodyLimitService<S>
 where
-    T1: FromRequest<S>,
-    S: Clone + Send + Sync,
+    S: Service<Request<Incoming>>,
 {
-    async fn from_request(req: Request<Incoming>, state: &S) -> Self {
-        let t1 = T1::from_request(req, &state).await;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
 
-        (t1,)
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
     }
+
+    fn call(&mut self, mut req: Request<Incoming>) -> Self::Future {
+        req.extensions_mut().insert(self.limit);
+        self.inner.call(req)
+    }
+}
+
+/// The body limit for this request, `None` if it has been disabled.
+pub(crate) fn body_limit(parts: &Parts) -> Option<usize> {
+    match parts.extensions.get::<DefaultBodyLimit>() {
+        Some(configured) => configured.limit,
+        None => Some(DEFAULT_BODY_LIMIT),
+    }
+}
+
+/// Rejects the request early if its `Content-Length` is over `limit`.
+pub(crate) fn check_content_length(
+    parts: &Parts,
+    limit: Option<usize>,
+) -> Result<(), BodyRejection> {
+    let Some(limit) = limit else {
+        return Ok(());
+    };
+
+    let length = parts
+        .headers
+        .get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok());
+
+    match length {
+        Some(length) if length > limit as u64 => Err(BodyRejection::LengthLimitExceeded { limit }),
+        _ => Ok(()),
+    }
+}
+
+/// Buffers the body, respecting the request's body limit.
+async fn read_body(parts: &Parts, body: Incoming) -> Result<Bytes, BodyRejection> {
+    let limit = body_limit(parts);
+    check_content_length(parts, limit)?;
+
+    let Some(limit) = limit else {
+        return body
+            .collect()
+            .await
+            .map(|collected| collected.to_bytes())
+            .map_err(|err| BodyRejection::FailedToBufferBody(err.into()));
+    };
+
+    match Limited::new(body, limit).collect().await {
+        Ok(collected) => Ok(collected.to_bytes()),
+        Err(err) if err.is::<LengthLimitError>() => {
+            Err(BodyRejection::LengthLimitExceeded { limit })
+        }
+        Err(err) => Err(BodyRejection::FailedToBufferBody(err)),
+    }
+}
+
+/// The lowercased media type of the request, without any parameters.
+fn mime_type(parts: &Parts) -> Option<String> {
+    let content_type = parts.headers.get(CONTENT_TYPE)?.to_str().ok()?;
+    let mime = content_type.split(';').next().unwrap_or_default();
+
+    Some(mime.trim().to_ascii_lowercase())
 }
 
-impl<S, T1, T2> FromRequest<S> for (T1, T2)
+fn has_json_content_type(parts: &Parts) -> bool {
+    mime_type(parts).is_some_and(|mime| {
+        mime == "application/json" || (mime.starts_with("application/") && mime.ends_with("+json"))
+    })
+}
+
+impl<S, T> FromRequest<S> for Json<T>
 where
-    T1: FromRequestParts<S>,
-    T2: FromRequest<S>,
+    T: for<'a> Deserialize<'a> + Send + Sync,
     S: Clone + Send + Sync,
 {
-    async fn from_request(req: Request<Incoming>, state: &S) -> Self {
+    type Rejection = JsonRejection;
+
+    async fn from_request(req: Request<Incoming>, _state: &S) -> Result<Self, Self::Rejection> {
         let (parts, body) = req.into_parts();
-        let t1 = T1::from_request_parts(parts.clone(), &state).await;
 
-        let req = Request::from_parts(parts, body);
-        let t2 = T2::from_request(req, &state).await;
+        if !has_json_content_type(&parts) {
+            return Err(JsonRejection::MissingJsonContentType);
+        }
 
-        (t1, t2)
+        let body = read_body(&parts, body)
+            .await
+            .map_err(JsonRejection::FailedToBufferBody)?;
+
+        serde_json::from_slice(&body)
+            .map(Json)
+            .map_err(|err| match err.classify() {
+                Category::Data => JsonRejection::JsonDataError(err),
+                Category::Syntax | Category::Eof | Category::Io => {
+                    JsonRejection::JsonSyntaxError(err)
+                }
+            })
     }
 }
 
-pub trait FromRequestParts<S>: Send + Sync {
+macro_rules! impl_from_request_tuple {
+    ([$($ty:ident),*], $last:ident) => {
+        impl<S, $($ty,)* $last> FromRequest<S> for ($($ty,)* $last,)
+        where
+            $($ty: FromRequestParts<S>,)*
+            $last: FromRequest<S>,
+            S: Clone + Send + Sync,
+        {
+            type Rejection = MiniResponse;
+
+            #[allow(non_snake_case)]
+            async fn from_request(
+                req: Request<Incoming>,
+                state: &S,
+            ) -> Result<Self, Self::Rejection> {
+                let (parts, body) = req.into_parts();
+                $(
+                    let $ty = $ty::from_request_parts(parts.clone(), state)
+                        .await
+                        .map_err(IntoMiniResponse::into_response)?;
+                )*
+
+                let req = Request::from_parts(parts, body);
+                let $last = $last::from_request(req, state)
+                    .await
+                    .map_err(IntoMiniResponse::into_response)?;
+
+                Ok(($($ty,)* $last,))
+            }
+        }
+    };
+}
+
+all_the_tuples!(impl_from_request_tuple);
+
+/// Extracts a value from the request head, leaving the body untouched.
+pub trait FromRequestParts<S>: Sized + Send + Sync {
+    type Rejection: IntoMiniResponse;
+
     fn from_request_parts(
         req: http::request::Parts,
         state: &S,
-    ) -> impl Future<Output = Self> + Send;
+    ) -> impl Future<Output = Result<Self, Self::Rejection>> + Send;
 }
 
 pub struct State<T>(pub T);
@@ -65,8 +250,10 @@ impl<S> FromRequestParts<S> for State<S>
 where
     S: Clone + Send + Sync,
 {
-    async fn from_request_parts(_req: Parts, state: &S) -> Self {
-        State(state.to_owned())
+    type Rejection = Infallible;
+
+    async fn from_request_parts(_req: Parts, state: &S) -> Result<Self, Self::Rejection> {
+        Ok(State(state.to_owned()))
     }
 }
 
@@ -74,7 +261,269 @@ impl<S> FromRequest<S> for State<S>
 where
     S: Clone + Send + Sync,
 {
-    async fn from_request(_req: Request<Incoming>, state: &S) -> Self {
-        State(state.to_owned())
+    type Rejection = Infallible;
+
+    async fn from_request(_req: Request<Incoming>, state: &S) -> Result<Self, Self::Rejection> {
+        Ok(State(state.to_owned()))
     }
 }
+
+/// Extracts the peer address of the connection the request came in on, a
+/// `SocketAddr` for TCP listeners.
+///
+/// Other connection info can be made available by implementing
+/// [`Connected`](crate::listener::Connected) and selecting it with
+/// [`Service::connect_info`](crate::Service::connect_info).
+#[derive(Clone, Copy, Debug)]
+pub struct ConnectInfo<T>(pub T);
+
+impl<S, T> FromRequestParts<S> for ConnectInfo<T>
+where
+    T: Clone + Send + Sync + 'static,
+    S: Clone + Send + Sync,
+{
+    type Rejection = ConnectInfoRejection;
+
+    async fn from_request_parts(req: Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        req.extensions
+            .get::<Self>()
+            .cloned()
+            .ok_or(ConnectInfoRejection::MissingConnectInfo)
+    }
+}
+
+impl<S, T> FromRequest<S> for ConnectInfo<T>
+where
+    T: Clone + Send + Sync + 'static,
+    S: Clone + Send + Sync,
+{
+    type Rejection = ConnectInfoRejection;
+
+    async fn from_request(req: Request<Incoming>, state: &S) -> Result<Self, Self::Rejection> {
+        let (parts, _) = req.into_parts();
+
+        Self::from_request_parts(parts, state).await
+    }
+}
+
+/// Extracts the parameters captured by a templated route such as `/users/{id}`.
+///
+/// `T` can be a single primitive, a tuple with one element per parameter, or any
+/// struct or map implementing `Deserialize` keyed by parameter name.
+pub struct Path<T>(pub T);
+
+impl<S, T> FromRequestParts<S> for Path<T>
+where
+    T: DeserializeOwned + Send + Sync,
+    S: Clone + Send + Sync,
+{
+    type Rejection = PathRejection;
+
+    async fn from_request_parts(req: Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let params = req
+            .extensions
+            .get::<Params>()
+            .ok_or(PathRejection::MissingPathParams)?;
+
+        let decoded = params
+            .iter()
+            .map(
+                |(key, value)| match percent_decode_str(value).decode_utf8() {
+                    Ok(value) => Ok((key.to_string(), value.into_owned())),
+                    Err(_) => Err(PathRejection::InvalidUtf8InPathParam {
+                        key: key.to_string(),
+                    }),
+                },
+            )
+            .collect::<Result<Vec<_>, _>>()?;
+
+        T::deserialize(PathDeserializer::new(&decoded))
+            .map(Path)
+            .map_err(PathRejection::FailedToDeserializePathParams)
+    }
+}
+
+impl<S, T> FromRequest<S> for Path<T>
+where
+    T: DeserializeOwned + Send + Sync,
+    S: Clone + Send + Sync,
+{
+    type Rejection = PathRejection;
+
+    async fn from_request(req: Request<Incoming>, state: &S) -> Result<Self, Self::Rejection> {
+        let (parts, _) = req.into_parts();
+
+        Self::from_request_parts(parts, state).await
+    }
+}
+
+/// Deserializes `application/x-www-form-urlencoded` data, keeping track of the
+/// field that failed. Repeated keys can be collected into a `Vec`.
+fn deserialize_form<T: DeserializeOwned>(
+    input: &[u8],
+) -> Result<T, serde_path_to_error::Error<serde_html_form::de::Error>> {
+    serde_path_to_error::deserialize(serde_html_form::Deserializer::from_bytes(input))
+}
+
+/// Extracts the URL query string, deserialized into `T`.
+///
+/// Repeated keys such as `?tag=a&tag=b` can be collected into a `Vec`, and
+/// missing keys map to `None` for `Option` fields.
+pub struct Query<T>(pub T);
+
+impl<S, T> FromRequestParts<S> for Query<T>
+where
+    T: DeserializeOwned + Send + Sync,
+    S: Clone + Send + Sync,
+{
+    type Rejection = QueryRejection;
+
+    async fn from_request_parts(req: Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let query = req.uri.query().unwrap_or_default();
+
+        deserialize_form(query.as_bytes())
+            .map(Query)
+            .map_err(QueryRejection::FailedToDeserializeQueryString)
+    }
+}
+
+impl<S, T> FromRequest<S> for Query<T>
+where
+    T: DeserializeOwned + Send + Sync,
+    S: Clone + Send + Sync,
+{
+    type Rejection = QueryRejection;
+
+    async fn from_request(req: Request<Incoming>, state: &S) -> Result<Self, Self::Rejection> {
+        let (parts, _) = req.into_parts();
+
+        Self::from_request_parts(parts, state).await
+    }
+}
+
+/// Extracts the URI the client requested. Unlike `Uri`, which has the prefix
+/// of a [nested](crate::Router::nest) router stripped, it holds the full
+/// path.
+#[derive(Clone, Debug)]
+pub struct OriginalUri(pub Uri);
+
+/// Extracts the template of the route that matched the request, such as
+/// `/users/{id}`. Routes of [nested](crate::Router::nest) routers include the
+/// prefix they're nested at.
+///
+/// It's also added to the extensions of the response, where middleware can
+/// read it once the request has been routed.
+#[derive(Clone, Debug)]
+pub struct MatchedPath(pub(crate) Arc<str>);
+
+impl MatchedPath {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl<S> FromRequestParts<S> for MatchedPath
+where
+    S: Clone + Send + Sync,
+{
+    type Rejection = MatchedPathRejection;
+
+    async fn from_request_parts(req: Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        req.extensions
+            .get::<Self>()
+            .cloned()
+            .ok_or(MatchedPathRejection::MatchedPathMissing)
+    }
+}
+
+impl<S> FromRequest<S> for MatchedPath
+where
+    S: Clone + Send + Sync,
+{
+    type Rejection = MatchedPathRejection;
+
+    async fn from_request(req: Request<Incoming>, state: &S) -> Result<Self, Self::Rejection> {
+        let (parts, _) = req.into_parts();
+
+        Self::from_request_parts(parts, state).await
+    }
+}
+
+/// Extracts the URL query string without parsing it. `None` if the URL has no
+/// query string.
+pub struct RawQuery(pub Option<String>);
+
+impl<S, T> FromRequest<S> for Form<T>
+where
+    T: DeserializeOwned + Send + Sync,
+    S: Clone + Send + Sync,
+{
+    type Rejection = FormRejection;
+
+    /// `GET` and `HEAD` requests carry the form in the query string, every
+    /// other method in an `application/x-www-form-urlencoded` body.
+    async fn from_request(req: Request<Incoming>, _state: &S) -> Result<Self, Self::Rejection> {
+        let (parts, body) = req.into_parts();
+
+        if parts.method == Method::GET || parts.method == Method::HEAD {
+            let query = parts.uri.query().unwrap_or_default();
+
+            return deserialize_form(query.as_bytes())
+                .map(Form)
+                .map_err(FormRejection::FailedToDeserializeForm);
+        }
+
+        if mime_type(&parts).as_deref() != Some("application/x-www-form-urlencoded") {
+            return Err(FormRejection::InvalidFormContentType);
+        }
+
+        let body = read_body(&parts, body)
+            .await
+            .map_err(FormRejection::FailedToBufferBody)?;
+
+        deserialize_form(&body)
+            .map(Form)
+            .map_err(FormRejection::FailedToDeserializeFormBody)
+    }
+}
+
+macro_rules! impl_from_request_parts_for_head {
+    ($($ty:ty => |$parts:ident| $extract:expr),* $(,)?) => {
+        $(
+            impl<S> FromRequestParts<S> for $ty
+            where
+                S: Clone + Send + Sync,
+            {
+                type Rejection = Infallible;
+
+                async fn from_request_parts($parts: Parts, _state: &S) -> Result<Self, Self::Rejection> {
+                    Ok($extract)
+                }
+            }
+
+            impl<S> FromRequest<S> for $ty
+            where
+                S: Clone + Send + Sync,
+            {
+                type Rejection = Infallible;
+
+                async fn from_request(req: Request<Incoming>, state: &S) -> Result<Self, Self::Rejection> {
+                    let (parts, _) = req.into_parts();
+
+                    Self::from_request_parts(parts, state).await
+                }
+            }
+        )*
+    };
+}
+
+impl_from_request_parts_for_head! {
+    HeaderMap => |parts| parts.headers,
+    Method => |parts| parts.method,
+    Uri => |parts| parts.uri,
+    RawQuery => |parts| RawQuery(parts.uri.query().map(str::to_string)),
+    OriginalUri => |parts| match parts.extensions.get::<OriginalUri>() {
+        Some(original) => original.clone(),
+        None => OriginalUri(parts.uri),
+    },
+}
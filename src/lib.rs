@@ -1,9 +1,24 @@
+#[macro_use]
+mod macros;
+
+pub mod body;
+mod de;
 pub mod endpoint;
 pub mod extractor;
+pub mod listener;
+mod matcher;
+pub mod method_router;
 pub mod middleware;
+pub mod multipart;
+pub mod rejection;
 pub mod response;
 mod router;
 mod service;
+pub mod sse;
+#[cfg(feature = "tls")]
+pub mod tls;
+pub mod ws;
 
+pub use method_router::MethodRouter;
 pub use router::Router;
 pub use service::Service;
@@ -0,0 +1,102 @@
+use std::io;
+
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+
+/// A source of connections for a [`Service`](crate::Service), such as a TCP
+/// or Unix domain socket.
+///
+/// The peer address of every connection is available to handlers through the
+/// [`ConnectInfo`](crate::extractor::ConnectInfo) extractor, unless another
+/// [`Connected`] type is selected.
+pub trait Listener: Send + 'static {
+    /// The stream of a single connection.
+    type Io: AsyncRead + AsyncWrite + Unpin + Send + 'static;
+    /// The address of the peer of a connection.
+    type Addr: Clone + Send + Sync + 'static;
+
+    /// Waits for the next connection.
+    fn accept(&mut self) -> impl Future<Output = io::Result<(Self::Io, Self::Addr)>> + Send;
+
+    /// The address the listener is bound to.
+    fn local_addr(&self) -> io::Result<Self::Addr>;
+}
+
+impl Listener for TcpListener {
+    type Io = tokio::net::TcpStream;
+    type Addr = std::net::SocketAddr;
+
+    async fn accept(&mut self) -> io::Result<(Self::Io, Self::Addr)> {
+        TcpListener::accept(self).await
+    }
+
+    fn local_addr(&self) -> io::Result<Self::Addr> {
+        TcpListener::local_addr(self)
+    }
+}
+
+#[cfg(unix)]
+impl Listener for tokio::net::UnixListener {
+    type Io = tokio::net::UnixStream;
+    type Addr = tokio::net::unix::SocketAddr;
+
+    async fn accept(&mut self) -> io::Result<(Self::Io, Self::Addr)> {
+        tokio::net::UnixListener::accept(self).await
+    }
+
+    fn local_addr(&self) -> io::Result<Self::Addr> {
+        tokio::net::UnixListener::local_addr(self)
+    }
+}
+
+/// A connection accepted by a [`Listener`], before any request was read.
+pub struct IncomingStream<'a, L: Listener> {
+    pub(crate) io: &'a L::Io,
+    pub(crate) remote_addr: L::Addr,
+}
+
+impl<L: Listener> IncomingStream<'_, L> {
+    pub fn io(&self) -> &L::Io {
+        self.io
+    }
+
+    pub fn remote_addr(&self) -> &L::Addr {
+        &self.remote_addr
+    }
+}
+
+/// Builds the connection info made available through
+/// [`ConnectInfo`](crate::extractor::ConnectInfo), once per connection.
+/// Select it with [`Service::connect_info`](crate::Service::connect_info).
+///
+/// ```no_run
+/// # #[cfg(unix)]
+/// # mod example {
+/// # use mini_axum::listener::{Connected, IncomingStream};
+/// # use tokio::net::UnixListener;
+/// #[derive(Clone)]
+/// struct PeerCred(Option<u32>);
+///
+/// impl Connected<IncomingStream<'_, UnixListener>> for PeerCred {
+///     fn connect_info(stream: IncomingStream<'_, UnixListener>) -> Self {
+///         PeerCred(stream.io().peer_cred().ok().map(|cred| cred.uid()))
+///     }
+/// }
+/// # }
+/// ```
+pub trait Connected<T>: Clone + Send + Sync + 'static {
+    fn connect_info(target: T) -> Self;
+}
+
+impl Connected<IncomingStream<'_, TcpListener>> for std::net::SocketAddr {
+    fn connect_info(stream: IncomingStream<'_, TcpListener>) -> Self {
+        stream.remote_addr
+    }
+}
+
+#[cfg(unix)]
+impl Connected<IncomingStream<'_, tokio::net::UnixListener>> for tokio::net::unix::SocketAddr {
+    fn connect_info(stream: IncomingStream<'_, tokio::net::UnixListener>) -> Self {
+        stream.remote_addr
+    }
+}
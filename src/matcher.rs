@@ -0,0 +1,586 @@
+use std::collections::HashMap;
+use std::fmt;
+
+/// A piece of a route template such as `/users/{id}/{*rest}`. Static pieces may
+/// span several segments, while parameters always cover exactly one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Piece {
+    Static(String),
+    Param(String),
+    Wildcard(String),
+}
+
+fn parse(route: &str) -> Result<Vec<Piece>, InsertError> {
+    if !route.starts_with('/') {
+        return Err(InsertError::Malformed {
+            route: route.to_string(),
+            reason: "routes must start with `/`",
+        });
+    }
+
+    let mut pieces = Vec::new();
+    let mut literal = String::new();
+    let segments: Vec<&str> = route[1..].split('/').collect();
+    let last = segments.len() - 1;
+
+    for (idx, segment) in segments.into_iter().enumerate() {
+        literal.push('/');
+
+        let name = segment.strip_prefix('{').and_then(|s| s.strip_suffix('}'));
+        let Some(name) = name else {
+            if segment.contains(['{', '}']) {
+                return Err(InsertError::Malformed {
+                    route: route.to_string(),
+                    reason: "parameters must span a whole segment",
+                });
+            }
+            literal.push_str(segment);
+            continue;
+        };
+
+        let (name, wildcard) = match name.strip_prefix('*') {
+            Some(name) => (name, true),
+            None => (name, false),
+        };
+
+        if name.is_empty() || name.contains(['{', '}', '*']) {
+            return Err(InsertError::Malformed {
+                route: route.to_string(),
+                reason: "parameter names must be non-empty and not contain `{`, `}` or `*`",
+            });
+        }
+
+        if wildcard && idx != last {
+            return Err(InsertError::Malformed {
+                route: route.to_string(),
+                reason: "wildcards must be the last segment",
+            });
+        }
+
+        pieces.push(Piece::Static(std::mem::take(&mut literal)));
+        pieces.push(match wildcard {
+            true => Piece::Wildcard(name.to_string()),
+            false => Piece::Param(name.to_string()),
+        });
+    }
+
+    if !literal.is_empty() {
+        pieces.push(Piece::Static(literal));
+    }
+
+    Ok(pieces)
+}
+
+/// Why a route could not be added to a [`Matcher`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum InsertError {
+    Malformed { route: String, reason: &'static str },
+    Conflict { route: String, existing: String },
+}
+
+impl fmt::Display for InsertError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Malformed { route, reason } => write!(f, "Invalid route `{route}`: {reason}"),
+            Self::Conflict { route, existing } => write!(
+                f,
+                "Route `{route}` conflicts with the existing route `{existing}`"
+            ),
+        }
+    }
+}
+
+/// The raw (still percent-encoded) values captured from a matched route.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Params(Vec<(String, String)>);
+
+impl Params {
+    fn push(&mut self, key: &str, value: &str) {
+        self.0.push((key.to_string(), value.to_string()));
+    }
+
+    fn pop(&mut self) {
+        self.0.pop();
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    /// Removes the value captured for `key`.
+    pub(crate) fn take(&mut self, key: &str) -> Option<String> {
+        let idx = self.0.iter().position(|(k, _)| k == key)?;
+
+        Some(self.0.remove(idx).1)
+    }
+
+    /// Appends the values captured by a nested route.
+    pub(crate) fn extend(&mut self, other: Params) {
+        self.0.extend(other.0);
+    }
+}
+
+pub(crate) struct Match<'a, T> {
+    /// The template of the matched route, e.g. `/users/{id}`.
+    pub(crate) route: &'a str,
+    pub(crate) value: &'a T,
+    pub(crate) params: Params,
+}
+
+/// A node of the radix tree. Static children are keyed by the first byte of
+/// their prefix, so at most one of them can match at any point of a lookup.
+#[derive(Clone, Debug, Default)]
+struct Node {
+    prefix: String,
+    children: Vec<Node>,
+    param: Option<Box<ParamNode>>,
+    wildcard: Option<WildcardNode>,
+    route: Option<usize>,
+}
+
+#[derive(Clone, Debug)]
+struct ParamNode {
+    name: String,
+    first_route: String,
+    node: Node,
+}
+
+#[derive(Clone, Debug)]
+struct WildcardNode {
+    name: String,
+    first_route: String,
+    route: usize,
+}
+
+fn common_prefix(a: &str, b: &str) -> usize {
+    a.bytes().zip(b.bytes()).take_while(|(a, b)| a == b).count()
+}
+
+impl Node {
+    fn static_child(prefix: &str) -> Self {
+        Self {
+            prefix: prefix.to_string(),
+            ..Self::default()
+        }
+    }
+
+    fn insert(&mut self, pieces: &[Piece], route: &str, id: usize) -> Result<(), InsertError> {
+        let Some((piece, rest)) = pieces.split_first() else {
+            self.route = Some(id);
+            return Ok(());
+        };
+
+        match piece {
+            Piece::Static(literal) => self.insert_static(literal, rest, route, id),
+            Piece::Param(name) => {
+                let param = self.param.get_or_insert_with(|| {
+                    Box::new(ParamNode {
+                        name: name.clone(),
+                        first_route: route.to_string(),
+                        node: Node::default(),
+                    })
+                });
+
+                if &param.name != name {
+                    return Err(InsertError::Conflict {
+                        route: route.to_string(),
+                        existing: param.first_route.clone(),
+                    });
+                }
+
+                param.node.insert(rest, route, id)
+            }
+            Piece::Wildcard(name) => match &self.wildcard {
+                Some(existing) if &existing.name != name => Err(InsertError::Conflict {
+                    route: route.to_string(),
+                    existing: existing.first_route.clone(),
+                }),
+                _ => {
+                    self.wildcard = Some(WildcardNode {
+                        name: name.clone(),
+                        first_route: route.to_string(),
+                        route: id,
+                    });
+                    Ok(())
+                }
+            },
+        }
+    }
+
+    fn insert_static(
+        &mut self,
+        literal: &str,
+        rest: &[Piece],
+        route: &str,
+        id: usize,
+    ) -> Result<(), InsertError> {
+        if literal.is_empty() {
+            return self.insert(rest, route, id);
+        }
+
+        let first = literal.as_bytes()[0];
+        let Some(child) = self
+            .children
+            .iter_mut()
+            .find(|child| child.prefix.as_bytes()[0] == first)
+        else {
+            let mut child = Node::static_child(literal);
+            child.insert(rest, route, id)?;
+            self.children.push(child);
+            return Ok(());
+        };
+
+        let shared = common_prefix(&child.prefix, literal);
+
+        if shared < child.prefix.len() {
+            let mut split = Node::static_child(&child.prefix[..shared]);
+            let mut existing = std::mem::take(child);
+            existing.prefix = existing.prefix[shared..].to_string();
+            split.children.push(existing);
+            *child = split;
+        }
+
+        child.insert_static(&literal[shared..], rest, route, id)
+    }
+
+    /// Looks up `path` below this node. Static children take precedence over
+    /// parameters, which take precedence over wildcards, backtracking when a
+    /// more specific branch doesn't lead to a route. Like parameters,
+    /// wildcards never capture an empty value.
+    fn at(&self, path: &str, params: &mut Params) -> Option<usize> {
+        if path.is_empty()
+            && let Some(route) = self.route
+        {
+            return Some(route);
+        }
+
+        if let Some(&first) = path.as_bytes().first()
+            && let Some(child) = self
+                .children
+                .iter()
+                .find(|child| child.prefix.as_bytes()[0] == first)
+            && let Some(rest) = path.strip_prefix(child.prefix.as_str())
+            && let Some(route) = child.at(rest, params)
+        {
+            return Some(route);
+        }
+
+        if let Some(param) = &self.param {
+            let end = path.find('/').unwrap_or(path.len());
+
+            if end > 0 {
+                params.push(&param.name, &path[..end]);
+
+                if let Some(route) = param.node.at(&path[end..], params) {
+                    return Some(route);
+                }
+
+                params.pop();
+            }
+        }
+
+        if let Some(wildcard) = &self.wildcard
+            && !path.is_empty()
+        {
+            params.push(&wildcard.name, path);
+            return Some(wildcard.route);
+        }
+
+        None
+    }
+}
+
+/// Maps route templates to values using a radix tree. The tree is built while
+/// routes are registered and is only read afterwards, so lookups need no locking.
+#[derive(Clone, Debug)]
+pub(crate) struct Matcher<T> {
+    root: Node,
+    ids: HashMap<String, usize>,
+    /// The route templates and their values, in registration order.
+    values: Vec<(String, T)>,
+}
+
+impl<T> Default for Matcher<T> {
+    fn default() -> Self {
+        Self {
+            root: Node::default(),
+            ids: HashMap::new(),
+            values: Vec::new(),
+        }
+    }
+}
+
+impl<T> Matcher<T> {
+    /// Adds `route`, replacing the value if the exact same template was
+    /// registered before.
+    pub(crate) fn insert(&mut self, route: &str, value: T) -> Result<(), InsertError> {
+        if let Some(&id) = self.ids.get(route) {
+            self.values[id].1 = value;
+            return Ok(());
+        }
+
+        let pieces = parse(route)?;
+        let id = self.values.len();
+
+        // Conflicts can only be found along existing branches, where the only
+        // changes made beforehand are prefix splits, so a failed insert leaves
+        // the tree matching exactly the same routes as before.
+        self.root.insert(&pieces, route, id)?;
+
+        self.ids.insert(route.to_string(), id);
+        self.values.push((route.to_string(), value));
+
+        Ok(())
+    }
+
+    pub(crate) fn at(&self, path: &str) -> Option<Match<'_, T>> {
+        let mut params = Params::default();
+        let id = self.root.at(path, &mut params)?;
+
+        let (route, value) = &self.values[id];
+
+        Some(Match {
+            route,
+            value,
+            params,
+        })
+    }
+
+    pub(crate) fn get_mut(&mut self, route: &str) -> Option<&mut T> {
+        let id = *self.ids.get(route)?;
+
+        Some(&mut self.values[id].1)
+    }
+
+    /// Every route template with its value, in registration order.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&str, &T)> {
+        self.values
+            .iter()
+            .map(|(route, value)| (route.as_str(), value))
+    }
+
+    pub(crate) fn values_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.values.iter_mut().map(|(_, value)| value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matcher(routes: &[&str]) -> Matcher<String> {
+        let mut matcher = Matcher::default();
+        for route in routes {
+            matcher.insert(route, route.to_string()).unwrap();
+        }
+        matcher
+    }
+
+    /// The route matching `path` and the values it captured.
+    fn lookup<'a>(
+        matcher: &'a Matcher<String>,
+        path: &str,
+    ) -> Option<(&'a str, Vec<(String, String)>)> {
+        let matched = matcher.at(path)?;
+        let params = matched
+            .params
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+
+        Some((matched.value.as_str(), params))
+    }
+
+    fn captured(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn matches_static_routes_exactly() {
+        let matcher = matcher(&["/", "/users", "/users/new"]);
+
+        assert_eq!(lookup(&matcher, "/"), Some(("/", vec![])));
+        assert_eq!(lookup(&matcher, "/users"), Some(("/users", vec![])));
+        assert_eq!(lookup(&matcher, "/users/new"), Some(("/users/new", vec![])));
+        assert_eq!(lookup(&matcher, "/users/"), None);
+        assert_eq!(lookup(&matcher, "/user"), None);
+        assert_eq!(lookup(&matcher, "/users/newer"), None);
+    }
+
+    #[test]
+    fn prefers_static_over_param_over_wildcard() {
+        let matcher = matcher(&["/files/{*path}", "/files/{name}", "/files/index"]);
+
+        assert_eq!(
+            lookup(&matcher, "/files/index"),
+            Some(("/files/index", vec![]))
+        );
+        assert_eq!(
+            lookup(&matcher, "/files/readme"),
+            Some(("/files/{name}", captured(&[("name", "readme")])))
+        );
+        assert_eq!(
+            lookup(&matcher, "/files/docs/readme"),
+            Some(("/files/{*path}", captured(&[("path", "docs/readme")])))
+        );
+    }
+
+    #[test]
+    fn backtracks_across_levels() {
+        let matcher = matcher(&[
+            "/users/new/edit",
+            "/users/{id}/posts",
+            "/{section}/{page}/comments",
+            "/{*rest}",
+        ]);
+
+        // `new` leads to the static branch, which has no `posts` child.
+        assert_eq!(
+            lookup(&matcher, "/users/new/posts"),
+            Some(("/users/{id}/posts", captured(&[("id", "new")])))
+        );
+        // Neither `/users/...` branch has `comments`, so the root parameter
+        // is tried with the parameters of the abandoned branches dropped.
+        assert_eq!(
+            lookup(&matcher, "/users/new/comments"),
+            Some((
+                "/{section}/{page}/comments",
+                captured(&[("section", "users"), ("page", "new")])
+            ))
+        );
+        assert_eq!(
+            lookup(&matcher, "/users/new/other"),
+            Some(("/{*rest}", captured(&[("rest", "users/new/other")])))
+        );
+    }
+
+    #[test]
+    fn splits_nodes_sharing_a_prefix() {
+        let matcher = matcher(&["/search", "/settings", "/se", "/sessions/{id}"]);
+
+        assert_eq!(lookup(&matcher, "/search"), Some(("/search", vec![])));
+        assert_eq!(lookup(&matcher, "/settings"), Some(("/settings", vec![])));
+        assert_eq!(lookup(&matcher, "/se"), Some(("/se", vec![])));
+        assert_eq!(
+            lookup(&matcher, "/sessions/1"),
+            Some(("/sessions/{id}", captured(&[("id", "1")])))
+        );
+        assert_eq!(lookup(&matcher, "/s"), None);
+        assert_eq!(lookup(&matcher, "/sea"), None);
+    }
+
+    #[test]
+    fn params_capture_a_single_non_empty_segment() {
+        let matcher = matcher(&["/users/{id}", "/users/{id}/posts/{post}"]);
+
+        assert_eq!(
+            lookup(&matcher, "/users/7/posts/hello"),
+            Some((
+                "/users/{id}/posts/{post}",
+                captured(&[("id", "7"), ("post", "hello")])
+            ))
+        );
+        assert_eq!(lookup(&matcher, "/users/"), None);
+        assert_eq!(lookup(&matcher, "/users/7/"), None);
+        assert_eq!(lookup(&matcher, "/users/7/posts/"), None);
+    }
+
+    #[test]
+    fn wildcards_need_a_non_empty_tail() {
+        let matcher = matcher(&["/f/{*rest}"]);
+
+        assert_eq!(
+            lookup(&matcher, "/f/a"),
+            Some(("/f/{*rest}", captured(&[("rest", "a")])))
+        );
+        assert_eq!(
+            lookup(&matcher, "/f/a/"),
+            Some(("/f/{*rest}", captured(&[("rest", "a/")])))
+        );
+        assert_eq!(lookup(&matcher, "/f/"), None);
+        assert_eq!(lookup(&matcher, "/f"), None);
+    }
+
+    #[test]
+    fn rejects_params_with_different_names() {
+        let mut matcher = matcher(&["/users/{id}"]);
+
+        assert_eq!(
+            matcher.insert("/users/{name}/posts", String::new()),
+            Err(InsertError::Conflict {
+                route: "/users/{name}/posts".to_string(),
+                existing: "/users/{id}".to_string(),
+            })
+        );
+        assert_eq!(matcher.insert("/users/{id}/posts", String::new()), Ok(()));
+    }
+
+    #[test]
+    fn rejects_wildcards_with_different_names() {
+        let mut matcher = matcher(&["/files/{*path}"]);
+
+        assert_eq!(
+            matcher.insert("/files/{*rest}", String::new()),
+            Err(InsertError::Conflict {
+                route: "/files/{*rest}".to_string(),
+                existing: "/files/{*path}".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn failed_inserts_leave_the_matcher_unchanged() {
+        let mut matcher = matcher(&["/users/{id}", "/users/list"]);
+
+        assert!(matcher.insert("/users/l{name}", String::new()).is_err());
+        assert!(matcher.insert("/users/{name}", String::new()).is_err());
+
+        assert_eq!(
+            lookup(&matcher, "/users/list"),
+            Some(("/users/list", vec![]))
+        );
+        assert_eq!(
+            lookup(&matcher, "/users/lisp"),
+            Some(("/users/{id}", captured(&[("id", "lisp")])))
+        );
+        assert_eq!(matcher.iter().count(), 2);
+    }
+
+    #[test]
+    fn rejects_malformed_routes() {
+        for route in [
+            "users",
+            "/users/{id",
+            "/users/id}",
+            "/users/x{id}",
+            "/users/{}",
+            "/users/{*}",
+            "/users/{a*b}",
+            "/files/{*path}/edit",
+        ] {
+            assert!(
+                matches!(
+                    Matcher::default().insert(route, ()),
+                    Err(InsertError::Malformed { .. })
+                ),
+                "{route} should be malformed"
+            );
+        }
+    }
+
+    #[test]
+    fn replaces_the_value_of_a_route_inserted_twice() {
+        let mut matcher = matcher(&["/a", "/b"]);
+        matcher.insert("/a", "again".to_string()).unwrap();
+
+        assert_eq!(lookup(&matcher, "/a"), Some(("again", vec![])));
+        assert_eq!(
+            matcher.iter().map(|(route, _)| route).collect::<Vec<_>>(),
+            ["/a", "/b"]
+        );
+        assert_eq!(matcher.at("/a").unwrap().route, "/a");
+    }
+}
@@ -0,0 +1,298 @@
+use std::sync::Arc;
+
+use futures::future::BoxFuture;
+use http::header::{ALLOW, CONTENT_LENGTH, HeaderValue};
+use http::{Method, Request, Response, StatusCode};
+use hyper::body::Body as _;
+use hyper::body::Incoming;
+use tower::util::BoxCloneSyncService;
+use tower::{Layer, Service};
+
+use crate::body::Body;
+use crate::endpoint::Handler;
+use crate::response::IntoMiniResponse;
+use crate::router::DynService;
+
+/// Turns the router's state into the service for a single method.
+type BoxedHandler<S> = Arc<dyn Fn(S) -> DynService + Send + Sync>;
+
+/// Dispatches requests for a single path to a handler depending on the
+/// request method.
+///
+/// ```no_run
+/// # use mini_axum::{Router, method_router::get};
+/// # async fn list_users() -> &'static str { "" }
+/// # async fn create_user() -> &'static str { "" }
+/// # let state = ();
+/// Router::with_state(state).route("/users", get(list_users).post(create_user));
+/// ```
+///
+/// `HEAD` requests are answered by the `GET` handler without a body unless a
+/// `HEAD` handler is registered, and `OPTIONS` requests get an empty response
+/// listing the allowed methods unless an `OPTIONS` handler is registered. Any
+/// other method receives `405 Method Not Allowed` with an `Allow` header.
+pub struct MethodRouter<S = ()> {
+    handlers: Vec<(Method, BoxedHandler<S>)>,
+}
+
+impl<S> Clone for MethodRouter<S> {
+    fn clone(&self) -> Self {
+        Self {
+            handlers: self.handlers.clone(),
+        }
+    }
+}
+
+impl<S> Default for MethodRouter<S> {
+    fn default() -> Self {
+        Self {
+            handlers: Vec::new(),
+        }
+    }
+}
+
+macro_rules! method_fns {
+    ($($name:ident => $method:ident),* $(,)?) => {
+        $(
+            #[doc = concat!("Routes `", stringify!($method), "` requests to `handler`.")]
+            pub fn $name<H, T, S>(handler: H) -> MethodRouter<S>
+            where
+                H: Handler<T, S>,
+                T: 'static,
+                S: Clone + Send + Sync + 'static,
+            {
+                on(Method::$method, handler)
+            }
+        )*
+
+        impl<S> MethodRouter<S>
+        where
+            S: Clone + Send + Sync + 'static,
+        {
+            $(
+                #[doc = concat!("Also routes `", stringify!($method), "` requests to `handler`.")]
+                pub fn $name<H, T>(self, handler: H) -> Self
+                where
+                    H: Handler<T, S>,
+                    T: 'static,
+                {
+                    self.on(Method::$method, handler)
+                }
+            )*
+        }
+    };
+}
+
+method_fns! {
+    get => GET,
+    head => HEAD,
+    post => POST,
+    put => PUT,
+    delete => DELETE,
+    patch => PATCH,
+    options => OPTIONS,
+}
+
+/// Routes requests with the given `method` to `handler`.
+pub fn on<H, T, S>(method: Method, handler: H) -> MethodRouter<S>
+where
+    H: Handler<T, S>,
+    T: 'static,
+    S: Clone + Send + Sync + 'static,
+{
+    MethodRouter::default().on(method, handler)
+}
+
+impl<S> MethodRouter<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    /// Routes requests with the given `method` to `handler`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a handler is already registered for `method`.
+    pub fn on<H, T>(mut self, method: Method, handler: H) -> Self
+    where
+        H: Handler<T, S>,
+        T: 'static,
+    {
+        let handler = Arc::new(handler);
+        let handler: BoxedHandler<S> = Arc::new(move |state| handler.clone().into_service(state));
+
+        if self.handlers.iter().any(|(existing, _)| existing == method) {
+            panic!("Overlapping method route: a handler for `{method}` is already registered");
+        }
+        self.handlers.push((method, handler));
+
+        self
+    }
+
+    /// Wraps every handler registered so far with `layer`.
+    pub fn layer<L>(self, layer: L) -> Self
+    where
+        L: Layer<DynService> + Clone + Send + Sync + 'static,
+        L::Service: Service<Request<Incoming>, Response = Response<Body>, Error = hyper::Error>
+            + Clone
+            + Send
+            + Sync
+            + 'static,
+        <L::Service as Service<Request<Incoming>>>::Future: Send + 'static,
+    {
+        let handlers = self
+            .handlers
+            .into_iter()
+            .map(|(method, handler)| {
+                let layer = layer.clone();
+                let layered: BoxedHandler<S> =
+                    Arc::new(move |state| BoxCloneSyncService::new(layer.layer(handler(state))));
+
+                (method, layered)
+            })
+            .collect();
+
+        Self { handlers }
+    }
+
+    /// Binds the router's state to every handler.
+    pub(crate) fn into_service(self, state: S) -> MethodService {
+        MethodService {
+            services: self
+                .handlers
+                .into_iter()
+                .map(|(method, handler)| (method, handler(state.clone())))
+                .collect(),
+        }
+    }
+}
+
+/// The `method_not_allowed_fallback` of the router that dispatched a request,
+/// called instead of responding with `405 Method Not Allowed`.
+#[derive(Clone)]
+pub(crate) struct MethodNotAllowedFallback(pub(crate) DynService);
+
+/// A [`MethodRouter`] whose handlers have been given the router's state.
+#[derive(Clone)]
+pub(crate) struct MethodService {
+    services: Vec<(Method, DynService)>,
+}
+
+impl MethodService {
+    /// Adds the methods of `other`, returning the first method both define
+    /// without changing `self`.
+    pub(crate) fn merge(&mut self, other: MethodService) -> Result<(), Method> {
+        if let Some((method, _)) = other
+            .services
+            .iter()
+            .find(|(method, _)| self.contains(method))
+        {
+            return Err(method.clone());
+        }
+
+        self.services.extend(other.services);
+        Ok(())
+    }
+
+    /// Routes every method of `self` to `service` instead, e.g. to the
+    /// handlers of `self` wrapped with layers.
+    pub(crate) fn routed_to(&self, service: DynService) -> MethodService {
+        MethodService {
+            services: self
+                .services
+                .iter()
+                .map(|(method, _)| (method.clone(), service.clone()))
+                .collect(),
+        }
+    }
+
+    /// Whether a request with `method` is answered by one of the handlers,
+    /// rather than with `405 Method Not Allowed` or an automatic `OPTIONS`
+    /// response.
+    pub(crate) fn handles(&self, method: &Method) -> bool {
+        self.contains(method) || (method == Method::HEAD && self.contains(&Method::GET))
+    }
+
+    fn contains(&self, method: &Method) -> bool {
+        self.services.iter().any(|(existing, _)| existing == method)
+    }
+
+    fn get(&self, method: &Method) -> Option<DynService> {
+        self.services
+            .iter()
+            .find(|(existing, _)| existing == method)
+            .map(|(_, service)| service.clone())
+    }
+
+    fn allow_header(&self) -> HeaderValue {
+        let mut allowed: Vec<&str> = self.services.iter().map(|(m, _)| m.as_str()).collect();
+
+        if self.contains(&Method::GET) && !self.contains(&Method::HEAD) {
+            allowed.push(Method::HEAD.as_str());
+        }
+        if !self.contains(&Method::OPTIONS) {
+            allowed.push(Method::OPTIONS.as_str());
+        }
+
+        HeaderValue::from_str(&allowed.join(",")).expect("method names are valid header values")
+    }
+}
+
+impl Service<Request<Incoming>> for MethodService {
+    type Response = Response<Body>;
+    type Error = hyper::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(
+        &mut self,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Request<Incoming>) -> Self::Future {
+        let method = req.method().clone();
+
+        if let Some(mut service) = self.get(&method) {
+            return Box::pin(async move { service.call(req).await });
+        }
+
+        if method == Method::HEAD
+            && let Some(mut service) = self.get(&Method::GET)
+        {
+            return Box::pin(async move {
+                let (mut parts, body) = service.call(req).await?.into_parts();
+
+                if let Some(len) = body.size_hint().exact() {
+                    parts
+                        .headers
+                        .entry(CONTENT_LENGTH)
+                        .or_insert(HeaderValue::from(len));
+                }
+
+                Ok(Response::from_parts(parts, Body::empty()))
+            });
+        }
+
+        let allow = self.allow_header();
+
+        if method != Method::OPTIONS
+            && let Some(MethodNotAllowedFallback(mut fallback)) =
+                req.extensions().get::<MethodNotAllowedFallback>().cloned()
+        {
+            return Box::pin(async move {
+                let mut res = fallback.call(req).await?;
+                res.headers_mut().entry(ALLOW).or_insert(allow);
+
+                Ok(res)
+            });
+        }
+
+        let mut res = match method {
+            Method::OPTIONS => StatusCode::NO_CONTENT.into_response(),
+            _ => (StatusCode::METHOD_NOT_ALLOWED, "Method not allowed").into_response(),
+        };
+        res.headers_mut().insert(ALLOW, allow);
+
+        Box::pin(async move { Ok(res.hyper_response()) })
+    }
+}
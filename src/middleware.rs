@@ -1,44 +1,647 @@
-use http::Request;
+use std::marker::PhantomData;
+use std::net::SocketAddr;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use futures::future::BoxFuture;
+use http::{Method, Request, Response, StatusCode, Uri, Version};
+use hyper::body::Body as _;
 use hyper::body::Incoming;
-use tower::Service;
+use tower::util::BoxCloneSyncService;
+use tower::{Layer, Service, ServiceExt};
 
-#[derive(Clone)]
-pub struct LogLayer;
+use crate::body::Body;
+use crate::extractor::{ConnectInfo, FromRequestParts, MatchedPath, OriginalUri};
+use crate::response::{IntoMiniResponse, MiniResponse};
+use crate::router::DynService;
+
+/// How [`LogLayer`] formats the message of each access log event.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// The Common Log Format used by most web servers:
+    ///
+    /// ```text
+    /// 127.0.0.1 - - [18/Oct/2026:09:37:10 +0000] "GET /users/1?full=true HTTP/1.1" 200 17
+    /// ```
+    #[default]
+    Common,
+    /// One JSON object per request:
+    ///
+    /// ```text
+    /// {"bytes":17,"latency_ms":0.41,"matched_path":"/users/{id}","method":"GET","path":"/users/1","peer":"127.0.0.1:51234","status":200,"time":"2026-10-18T09:37:10Z"}
+    /// ```
+    Json,
+}
+
+/// Logs an access log line for every request through `tracing`, as an
+/// `INFO` event with the `mini_axum::access` target.
+///
+/// Besides the formatted message, each event carries the `method`, `path`,
+/// `matched_path`, `status`, `latency_ms`, `bytes` and `peer` fields for
+/// subscribers that record structured data. Nothing is logged unless a
+/// subscriber is installed:
+///
+/// ```no_run
+/// # use mini_axum::{Router, method_router::get};
+/// # use mini_axum::middleware::{LogFormat, LogLayer};
+/// # async fn get_user() -> &'static str { "" }
+/// tracing_subscriber::fmt().init();
+///
+/// let router = Router::stateless()
+///     .route("/users/{id}", get(get_user))
+///     .layer(LogLayer::new().format(LogFormat::Json));
+/// ```
+///
+/// The latency is measured until the response head is ready, and `bytes` is
+/// only known for bodies of a fixed size, not for streamed ones. The peer is
+/// the `SocketAddr` recorded by [`ConnectInfo`], so it's missing for Unix
+/// sockets or custom connection info.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LogLayer {
+    format: LogFormat,
+}
+
+impl LogLayer {
+    /// Logs in the [Common Log Format](LogFormat::Common).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn format(mut self, format: LogFormat) -> Self {
+        self.format = format;
+        self
+    }
+}
 
 impl<S> tower::Layer<S> for LogLayer {
     type Service = LogService<S>;
 
     fn layer(&self, inner: S) -> Self::Service {
-        LogService { inner }
+        LogService {
+            inner,
+            format: self.format,
+        }
     }
 }
 
 #[derive(Clone)]
 pub struct LogService<S> {
     inner: S,
+    format: LogFormat,
 }
 
 impl<S> tower::Service<Request<Incoming>> for LogService<S>
 where
-    S: Service<Request<Incoming>> + Clone,
+    S: Service<Request<Incoming>, Response = Response<Body>> + Clone,
+    S::Future: Send + 'static,
 {
     type Error = S::Error;
-    type Response = S::Response;
-    type Future = S::Future;
+    type Response = Response<Body>;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
 
     fn call(&mut self, req: Request<Incoming>) -> Self::Future {
-        let (parts, body) = req.into_parts();
-        let req = Request::from_parts(parts.clone(), body);
+        let time = SystemTime::now();
+        let start = Instant::now();
+
+        let uri = match req.extensions().get::<OriginalUri>() {
+            Some(OriginalUri(uri)) => uri.clone(),
+            None => req.uri().clone(),
+        };
+        let mut entry = AccessLogEntry {
+            time,
+            method: req.method().clone(),
+            uri,
+            version: req.version(),
+            matched_path: req.extensions().get::<MatchedPath>().cloned(),
+            peer: req
+                .extensions()
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| *addr),
+            status: StatusCode::OK,
+            latency: Duration::ZERO,
+            bytes: None,
+        };
+        let format = self.format;
 
         let mut service = self.inner.clone();
+        let res = service.call(req);
+
+        Box::pin(async move {
+            let res = res.await;
+            entry.latency = start.elapsed();
 
-        service.call(req)
+            let res = match res {
+                Ok(res) => res,
+                // The connection is closed without a response, which is
+                // logged like an internal error.
+                Err(err) => {
+                    entry.status = StatusCode::INTERNAL_SERVER_ERROR;
+                    entry.log(format);
+                    return Err(err);
+                }
+            };
+
+            entry.status = res.status();
+            entry.bytes = res.body().size_hint().exact();
+            // Routers nested below the layer only record the full route in
+            // the response.
+            if let Some(matched_path) = res.extensions().get::<MatchedPath>() {
+                entry.matched_path = Some(matched_path.clone());
+            }
+            entry.log(format);
+
+            Ok(res)
+        })
     }
 
     fn poll_ready(
         &mut self,
-        cx: &mut std::task::Context<'_>,
+        _cx: &mut std::task::Context<'_>,
     ) -> std::task::Poll<Result<(), Self::Error>> {
         std::task::Poll::Ready(Ok(()))
     }
 }
+
+/// What [`LogService`] knows about a request once it has been answered.
+struct AccessLogEntry {
+    time: SystemTime,
+    method: Method,
+    uri: Uri,
+    version: Version,
+    matched_path: Option<MatchedPath>,
+    peer: Option<SocketAddr>,
+    status: StatusCode,
+    latency: Duration,
+    bytes: Option<u64>,
+}
+
+impl AccessLogEntry {
+    fn log(&self, format: LogFormat) {
+        let message = match format {
+            LogFormat::Common => self.common(),
+            LogFormat::Json => self.json(),
+        };
+
+        tracing::info!(
+            target: "mini_axum::access",
+            method = %self.method,
+            path = self.uri.path(),
+            matched_path = self.matched_path.as_ref().map(MatchedPath::as_str),
+            status = self.status.as_u16(),
+            latency_ms = self.latency.as_secs_f64() * 1000.0,
+            bytes = self.bytes,
+            peer = self.peer.map(tracing::field::display),
+            "{message}"
+        );
+    }
+
+    fn common(&self) -> String {
+        let (year, month, day, hour, minute, second) = utc(self.time);
+        let host = match self.peer {
+            Some(peer) => peer.ip().to_string(),
+            None => "-".to_string(),
+        };
+        let target = self
+            .uri
+            .path_and_query()
+            .map_or(self.uri.path(), |path_and_query| path_and_query.as_str());
+        let bytes = match self.bytes {
+            Some(bytes) => bytes.to_string(),
+            None => "-".to_string(),
+        };
+
+        format!(
+            "{host} - - [{day:02}/{}/{year}:{hour:02}:{minute:02}:{second:02} +0000] \"{} {target} {:?}\" {} {bytes}",
+            MONTHS[month as usize - 1],
+            self.method,
+            self.version,
+            self.status.as_u16(),
+        )
+    }
+
+    fn json(&self) -> String {
+        let (year, month, day, hour, minute, second) = utc(self.time);
+
+        serde_json::json!({
+            "time": format!("{year}-{month:02}-{day:02}T{hour:02}:{minute:02}:{second:02}Z"),
+            "method": self.method.as_str(),
+            "path": self.uri.path(),
+            "matched_path": self.matched_path.as_ref().map(MatchedPath::as_str),
+            "status": self.status.as_u16(),
+            "latency_ms": self.latency.as_secs_f64() * 1000.0,
+            "bytes": self.bytes,
+            "peer": self.peer.map(|peer| peer.to_string()),
+        })
+        .to_string()
+    }
+}
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Splits `time` into its UTC year, month, day, hour, minute and second.
+fn utc(time: SystemTime) -> (u64, u64, u64, u64, u64, u64) {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    let (days, secs) = (secs / 86_400, secs % 86_400);
+
+    // Converts days since the epoch to a date of the proleptic Gregorian
+    // calendar, with years starting in March so leap days come last.
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = era * 400 + year_of_era + u64::from(month <= 2);
+
+    (year, month, day, secs / 3_600, secs % 3_600 / 60, secs % 60)
+}
+
+/// The rest of the middleware stack and the handler, passed to middleware
+/// written with [`from_fn`].
+pub struct Next {
+    inner: DynService,
+}
+
+impl Next {
+    /// Passes `req` on to the inner service. An error from it is turned
+    /// into a `500 Internal Server Error` response.
+    pub async fn run(self, req: Request<Incoming>) -> Response<Body> {
+        match self.inner.oneshot(req).await {
+            Ok(res) => res,
+            Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+                .into_response()
+                .hyper_response(),
+        }
+    }
+}
+
+/// Creates a layer from an async function taking any number of
+/// [`FromRequestParts`] extractors, the request and a [`Next`]:
+///
+/// ```no_run
+/// # use hyper::{HeaderMap, Request, StatusCode, body::Incoming, header::AUTHORIZATION};
+/// # use mini_axum::Router;
+/// # use mini_axum::middleware::{Next, from_fn};
+/// # use mini_axum::response::{IntoMiniResponse, MiniResponse};
+/// async fn require_auth(headers: HeaderMap, req: Request<Incoming>, next: Next) -> MiniResponse {
+///     match headers.get(AUTHORIZATION) {
+///         Some(_) => next.run(req).await.into_response(),
+///         None => StatusCode::UNAUTHORIZED.into_response(),
+///     }
+/// }
+///
+/// # let router = Router::stateless();
+/// router.route_layer(from_fn(require_auth));
+/// ```
+///
+/// If an extractor rejects the request, the rejection is sent as the
+/// response without calling the function.
+pub fn from_fn<F, T>(f: F) -> FromFnLayer<F, (), T> {
+    from_fn_with_state((), f)
+}
+
+/// Like [`from_fn`], with `state` available through the
+/// [`State`](crate::extractor::State) extractor.
+pub fn from_fn_with_state<F, S, T>(state: S, f: F) -> FromFnLayer<F, S, T> {
+    FromFnLayer {
+        f,
+        state,
+        _marker: PhantomData,
+    }
+}
+
+/// Creates a layer from an async function transforming the request before
+/// it reaches the inner service. The function takes any number of
+/// [`FromRequestParts`] extractors followed by the request, and returns
+/// either the request or a `Result` whose error is sent as the response:
+///
+/// ```no_run
+/// # use hyper::{Request, body::Incoming, header::HeaderValue};
+/// # use mini_axum::{Router, middleware::map_request};
+/// async fn add_request_id(mut req: Request<Incoming>) -> Request<Incoming> {
+///     req.headers_mut().insert("x-request-id", HeaderValue::from_static("42"));
+///     req
+/// }
+///
+/// # let router = Router::stateless();
+/// router.layer(map_request(add_request_id));
+/// ```
+pub fn map_request<F, T>(f: F) -> MapRequestLayer<F, (), T> {
+    map_request_with_state((), f)
+}
+
+/// Like [`map_request`], with `state` available through the
+/// [`State`](crate::extractor::State) extractor.
+pub fn map_request_with_state<F, S, T>(state: S, f: F) -> MapRequestLayer<F, S, T> {
+    MapRequestLayer {
+        f,
+        state,
+        _marker: PhantomData,
+    }
+}
+
+/// Creates a layer from an async function transforming the response of the
+/// inner service. The function takes any number of [`FromRequestParts`]
+/// extractors, run before the request is passed on, followed by the
+/// response:
+///
+/// ```no_run
+/// # use hyper::{Response, header::{CACHE_CONTROL, HeaderValue}};
+/// # use mini_axum::{Router, body::Body, middleware::map_response};
+/// async fn no_cache(mut res: Response<Body>) -> Response<Body> {
+///     res.headers_mut().insert(CACHE_CONTROL, HeaderValue::from_static("no-store"));
+///     res
+/// }
+///
+/// # let router = Router::stateless();
+/// router.layer(map_response(no_cache));
+/// ```
+pub fn map_response<F, T>(f: F) -> MapResponseLayer<F, (), T> {
+    map_response_with_state((), f)
+}
+
+/// Like [`map_response`], with `state` available through the
+/// [`State`](crate::extractor::State) extractor.
+pub fn map_response_with_state<F, S, T>(state: S, f: F) -> MapResponseLayer<F, S, T> {
+    MapResponseLayer {
+        f,
+        state,
+        _marker: PhantomData,
+    }
+}
+
+/// What a [`map_request`] function can return: the request to pass on, or a
+/// `Result` whose error is sent as the response instead.
+pub trait IntoMapRequestResult {
+    fn into_map_request_result(self) -> Result<Request<Incoming>, MiniResponse>;
+}
+
+impl IntoMapRequestResult for Request<Incoming> {
+    fn into_map_request_result(self) -> Result<Request<Incoming>, MiniResponse> {
+        Ok(self)
+    }
+}
+
+impl<E> IntoMapRequestResult for Result<Request<Incoming>, E>
+where
+    E: IntoMiniResponse,
+{
+    fn into_map_request_result(self) -> Result<Request<Incoming>, MiniResponse> {
+        self.map_err(IntoMiniResponse::into_response)
+    }
+}
+
+macro_rules! middleware_layer {
+    ($(#[$doc:meta])* $layer:ident => $service:ident) => {
+        $(#[$doc])*
+        pub struct $layer<F, S, T> {
+            f: F,
+            state: S,
+            _marker: PhantomData<fn() -> T>,
+        }
+
+        impl<F, S, T> Clone for $layer<F, S, T>
+        where
+            F: Clone,
+            S: Clone,
+        {
+            fn clone(&self) -> Self {
+                Self {
+                    f: self.f.clone(),
+                    state: self.state.clone(),
+                    _marker: PhantomData,
+                }
+            }
+        }
+
+        impl<I, F, S, T> Layer<I> for $layer<F, S, T>
+        where
+            F: Clone,
+            S: Clone,
+        {
+            type Service = $service<F, S, I, T>;
+
+            fn layer(&self, inner: I) -> Self::Service {
+                $service {
+                    f: self.f.clone(),
+                    state: self.state.clone(),
+                    inner,
+                    _marker: PhantomData,
+                }
+            }
+        }
+
+        #[doc = concat!("The service created by [`", stringify!($layer), "`].")]
+        pub struct $service<F, S, I, T> {
+            f: F,
+            state: S,
+            inner: I,
+            _marker: PhantomData<fn() -> T>,
+        }
+
+        impl<F, S, I, T> Clone for $service<F, S, I, T>
+        where
+            F: Clone,
+            S: Clone,
+            I: Clone,
+        {
+            fn clone(&self) -> Self {
+                Self {
+                    f: self.f.clone(),
+                    state: self.state.clone(),
+                    inner: self.inner.clone(),
+                    _marker: PhantomData,
+                }
+            }
+        }
+    };
+}
+
+middleware_layer! {
+    /// A layer running an async function as middleware, created with
+    /// [`from_fn`] or [`from_fn_with_state`].
+    FromFnLayer => FromFn
+}
+
+middleware_layer! {
+    /// A layer transforming requests with an async function, created with
+    /// [`map_request`] or [`map_request_with_state`].
+    MapRequestLayer => MapRequest
+}
+
+middleware_layer! {
+    /// A layer transforming responses with an async function, created with
+    /// [`map_response`] or [`map_response_with_state`].
+    MapResponseLayer => MapResponse
+}
+
+/// Runs the extractors `$ty` on the head of `$req`, returning early with the
+/// rejection of the first one that fails.
+macro_rules! extract_parts {
+    ($req:ident, $state:ident, [$($ty:ident),*]) => {
+        let (parts, body) = $req.into_parts();
+        $(
+            let $ty = match $ty::from_request_parts(parts.clone(), &$state).await {
+                Ok(value) => value,
+                Err(rejection) => return Ok(rejection.into_response().hyper_response()),
+            };
+        )*
+        let $req = Request::from_parts(parts, body);
+    };
+}
+
+macro_rules! impl_middleware {
+    ([$($ty:ident),*], $last:ident) => {
+        impl_middleware!($($ty,)* $last);
+    };
+    ($($ty:ident),*) => {
+        impl<F, Fut, Out, S, I, $($ty,)*> Service<Request<Incoming>> for FromFn<F, S, I, ($($ty,)*)>
+        where
+            F: Fn($($ty,)* Request<Incoming>, Next) -> Fut + Clone + Send + 'static,
+            Fut: Future<Output = Out> + Send + 'static,
+            Out: IntoMiniResponse,
+            S: Clone + Send + Sync + 'static,
+            I: Service<Request<Incoming>, Response = Response<Body>, Error = hyper::Error>
+                + Clone
+                + Send
+                + Sync
+                + 'static,
+            I::Future: Send + 'static,
+            $($ty: FromRequestParts<S> + Send,)*
+        {
+            type Response = Response<Body>;
+            type Error = hyper::Error;
+            type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+            fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+                Poll::Ready(Ok(()))
+            }
+
+            #[allow(non_snake_case, unused_variables)]
+            fn call(&mut self, req: Request<Incoming>) -> Self::Future {
+                let f = self.f.clone();
+                let state = self.state.clone();
+                let next = Next {
+                    inner: BoxCloneSyncService::new(self.inner.clone()),
+                };
+
+                Box::pin(async move {
+                    extract_parts!(req, state, [$($ty),*]);
+
+                    Ok(f($($ty,)* req, next).await.into_response().hyper_response())
+                })
+            }
+        }
+
+        impl<F, Fut, Out, S, I, $($ty,)*> Service<Request<Incoming>> for MapRequest<F, S, I, ($($ty,)*)>
+        where
+            F: Fn($($ty,)* Request<Incoming>) -> Fut + Clone + Send + 'static,
+            Fut: Future<Output = Out> + Send + 'static,
+            Out: IntoMapRequestResult,
+            S: Clone + Send + Sync + 'static,
+            I: Service<Request<Incoming>, Response = Response<Body>, Error = hyper::Error>
+                + Clone
+                + Send
+                + 'static,
+            I::Future: Send + 'static,
+            $($ty: FromRequestParts<S> + Send,)*
+        {
+            type Response = Response<Body>;
+            type Error = hyper::Error;
+            type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+            fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+                Poll::Ready(Ok(()))
+            }
+
+            #[allow(non_snake_case, unused_variables)]
+            fn call(&mut self, req: Request<Incoming>) -> Self::Future {
+                let f = self.f.clone();
+                let state = self.state.clone();
+                let inner = self.inner.clone();
+
+                Box::pin(async move {
+                    extract_parts!(req, state, [$($ty),*]);
+
+                    match f($($ty,)* req).await.into_map_request_result() {
+                        Ok(req) => inner.oneshot(req).await,
+                        Err(res) => Ok(res.hyper_response()),
+                    }
+                })
+            }
+        }
+
+        impl<F, Fut, Out, S, I, $($ty,)*> Service<Request<Incoming>> for MapResponse<F, S, I, ($($ty,)*)>
+        where
+            F: Fn($($ty,)* Response<Body>) -> Fut + Clone + Send + 'static,
+            Fut: Future<Output = Out> + Send + 'static,
+            Out: IntoMiniResponse,
+            S: Clone + Send + Sync + 'static,
+            I: Service<Request<Incoming>, Response = Response<Body>, Error = hyper::Error>
+                + Clone
+                + Send
+                + 'static,
+            I::Future: Send + 'static,
+            $($ty: FromRequestParts<S> + Send,)*
+        {
+            type Response = Response<Body>;
+            type Error = hyper::Error;
+            type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+            fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+                Poll::Ready(Ok(()))
+            }
+
+            #[allow(non_snake_case, unused_variables)]
+            fn call(&mut self, req: Request<Incoming>) -> Self::Future {
+                let f = self.f.clone();
+                let state = self.state.clone();
+                let inner = self.inner.clone();
+
+                Box::pin(async move {
+                    extract_parts!(req, state, [$($ty),*]);
+
+                    let res = inner.oneshot(req).await?;
+                    Ok(f($($ty,)* res).await.into_response().hyper_response())
+                })
+            }
+        }
+    };
+}
+
+impl_middleware!();
+all_the_tuples!(impl_middleware);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc_at(secs: u64) -> (u64, u64, u64, u64, u64, u64) {
+        utc(UNIX_EPOCH + Duration::from_secs(secs))
+    }
+
+    #[test]
+    fn converts_timestamps_to_utc_dates() {
+        assert_eq!(utc_at(0), (1970, 1, 1, 0, 0, 0));
+        assert_eq!(utc_at(946_684_799), (1999, 12, 31, 23, 59, 59));
+        assert_eq!(utc_at(1_792_316_230), (2026, 10, 18, 9, 37, 10));
+    }
+
+    #[test]
+    fn handles_leap_days() {
+        assert_eq!(utc_at(951_782_400), (2000, 2, 29, 0, 0, 0));
+        // 2100 isn't a leap year.
+        assert_eq!(utc_at(4_107_628_799), (2100, 3, 1, 23, 59, 59));
+    }
+}
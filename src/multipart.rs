@@ -0,0 +1,281 @@
+use std::fmt;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::Stream;
+use http::header::CONTENT_TYPE;
+use http::{HeaderMap, Request, StatusCode};
+use http_body_util::BodyExt;
+use hyper::body::{Bytes, Incoming};
+use tower::{Layer, Service};
+
+use crate::extractor::{FromRequest, body_limit, check_content_length};
+use crate::rejection::MultipartRejection;
+use crate::response::{IntoMiniResponse, MiniResponse};
+
+/// Extracts a `multipart/form-data` body as a stream of fields, reading the
+/// body from the connection only as fields are consumed.
+///
+/// ```no_run
+/// # use mini_axum::multipart::{Multipart, MultipartError};
+/// async fn upload(mut multipart: Multipart) -> Result<&'static str, MultipartError> {
+///     while let Some(mut field) = multipart.next_field().await? {
+///         while let Some(chunk) = field.chunk().await? {
+///             // write `chunk` somewhere
+///         }
+///     }
+///
+///     Ok("uploaded")
+/// }
+/// ```
+///
+/// Size limits can be configured with the [`MultipartLimits`] layer.
+pub struct Multipart {
+    inner: multer::Multipart<'static>,
+}
+
+impl<S> FromRequest<S> for Multipart
+where
+    S: Clone + Send + Sync,
+{
+    type Rejection = MultipartRejection;
+
+    async fn from_request(req: Request<Incoming>, _state: &S) -> Result<Self, Self::Rejection> {
+        let (parts, body) = req.into_parts();
+
+        let content_type = parts
+            .headers
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .ok_or(MultipartRejection::InvalidBoundary)?;
+        let boundary = multer::parse_boundary(content_type)
+            .map_err(|_| MultipartRejection::InvalidBoundary)?;
+
+        let mut limits = parts
+            .extensions
+            .get::<MultipartLimits>()
+            .cloned()
+            .unwrap_or_default();
+
+        if limits.whole_stream.is_none() {
+            let limit = body_limit(&parts);
+            check_content_length(&parts, limit).map_err(MultipartRejection::FailedToBufferBody)?;
+            limits.whole_stream = limit.map(|limit| limit as u64);
+        }
+
+        let inner = multer::Multipart::with_constraints(
+            body.into_data_stream(),
+            boundary,
+            limits.constraints(),
+        );
+
+        Ok(Self { inner })
+    }
+}
+
+impl Multipart {
+    /// Returns the next field, or `None` once every field has been read.
+    pub async fn next_field(&mut self) -> Result<Option<Field>, MultipartError> {
+        let field = self.inner.next_field().await?;
+
+        Ok(field.map(|inner| Field { inner }))
+    }
+}
+
+impl Stream for Multipart {
+    type Item = Result<Field, MultipartError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner
+            .poll_next_field(cx)
+            .map(|field| field.map(|f| f.map(|inner| Field { inner })).transpose())
+            .map_err(MultipartError::from)
+    }
+}
+
+/// A single field of a multipart body. Its data is a stream of chunks, which
+/// has to be read before moving on to the next field.
+pub struct Field {
+    inner: multer::Field<'static>,
+}
+
+impl Field {
+    /// The `name` of the field from its `Content-Disposition` header.
+    pub fn name(&self) -> Option<&str> {
+        self.inner.name()
+    }
+
+    /// The `filename` of the field from its `Content-Disposition` header.
+    pub fn file_name(&self) -> Option<&str> {
+        self.inner.file_name()
+    }
+
+    /// The field's `Content-Type`.
+    pub fn content_type(&self) -> Option<&str> {
+        self.inner.content_type().map(|mime| mime.as_ref())
+    }
+
+    pub fn headers(&self) -> &HeaderMap {
+        self.inner.headers()
+    }
+
+    /// Reads the next chunk of the field's data, or `None` at its end.
+    pub async fn chunk(&mut self) -> Result<Option<Bytes>, MultipartError> {
+        Ok(self.inner.chunk().await?)
+    }
+
+    /// Buffers the rest of the field's data.
+    pub async fn bytes(self) -> Result<Bytes, MultipartError> {
+        Ok(self.inner.bytes().await?)
+    }
+
+    /// Buffers the rest of the field's data as text.
+    pub async fn text(self) -> Result<String, MultipartError> {
+        Ok(self.inner.text().await?)
+    }
+}
+
+impl Stream for Field {
+    type Item = Result<Bytes, MultipartError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.inner)
+            .poll_next(cx)
+            .map(|chunk| chunk.map(|c| c.map_err(MultipartError::from)))
+    }
+}
+
+/// An error raised while reading a multipart body, such as a malformed part
+/// or an exceeded size limit.
+#[derive(Debug)]
+pub struct MultipartError {
+    source: multer::Error,
+}
+
+impl MultipartError {
+    pub fn status(&self) -> StatusCode {
+        match self.source {
+            multer::Error::FieldSizeExceeded { .. } | multer::Error::StreamSizeExceeded { .. } => {
+                StatusCode::PAYLOAD_TOO_LARGE
+            }
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+
+    /// Whether a per-field or whole-body size limit was exceeded.
+    pub fn is_limit_exceeded(&self) -> bool {
+        self.status() == StatusCode::PAYLOAD_TOO_LARGE
+    }
+}
+
+impl From<multer::Error> for MultipartError {
+    fn from(source: multer::Error) -> Self {
+        Self { source }
+    }
+}
+
+impl fmt::Display for MultipartError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Error parsing `multipart/form-data` request: {}",
+            self.source
+        )
+    }
+}
+
+impl std::error::Error for MultipartError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+impl IntoMiniResponse for MultipartError {
+    fn into_response(self) -> MiniResponse {
+        (self.status(), self.to_string()).into_response()
+    }
+}
+
+/// Size limits for [`Multipart`] bodies, applied as a layer:
+///
+/// ```no_run
+/// # use mini_axum::{Router, multipart::MultipartLimits};
+/// # let router = Router::stateless();
+/// router.layer(MultipartLimits::new().per_field(10 * 1024 * 1024).whole_stream(50 * 1024 * 1024))
+/// # ;
+/// ```
+///
+/// No per-field limit is set by default, and the whole body is limited by
+/// [`DefaultBodyLimit`](crate::extractor::DefaultBodyLimit) unless
+/// `whole_stream` is set.
+#[derive(Clone, Debug, Default)]
+pub struct MultipartLimits {
+    per_field: Option<u64>,
+    whole_stream: Option<u64>,
+}
+
+impl MultipartLimits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The maximum number of bytes in a single field.
+    pub fn per_field(mut self, limit: u64) -> Self {
+        self.per_field = Some(limit);
+        self
+    }
+
+    /// The maximum number of bytes in the whole body.
+    pub fn whole_stream(mut self, limit: u64) -> Self {
+        self.whole_stream = Some(limit);
+        self
+    }
+
+    fn constraints(&self) -> multer::Constraints {
+        let mut size_limit = multer::SizeLimit::new();
+
+        if let Some(limit) = self.per_field {
+            size_limit = size_limit.per_field(limit);
+        }
+        if let Some(limit) = self.whole_stream {
+            size_limit = size_limit.whole_stream(limit);
+        }
+
+        multer::Constraints::new().size_limit(size_limit)
+    }
+}
+
+impl<S> Layer<S> for MultipartLimits {
+    type Service = MultipartLimitsService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        MultipartLimitsService {
+            inner,
+            limits: self.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct MultipartLimitsService<S> {
+    inner: S,
+    limits: MultipartLimits,
+}
+
+impl<S> Service<Request<Incoming>> for MultipartLimitsService<S>
+where
+    S: Service<Request<Incoming>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<Incoming>) -> Self::Future {
+        req.extensions_mut().insert(self.limits.clone());
+        self.inner.call(req)
+    }
+}
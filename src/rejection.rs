@@ -0,0 +1,413 @@
+use std::convert::Infallible;
+use std::fmt;
+
+use http::StatusCode;
+
+use crate::response::{IntoMiniResponse, MiniResponse};
+
+pub use crate::de::PathDeserializationError;
+
+fn plain_text(code: StatusCode, message: String) -> MiniResponse {
+    (code, message).into_response()
+}
+
+impl IntoMiniResponse for Infallible {
+    fn into_response(self) -> MiniResponse {
+        match self {}
+    }
+}
+
+/// Raised by body-consuming extractors when the body can't be buffered.
+#[derive(Debug)]
+pub enum BodyRejection {
+    /// The body was larger than the limit set by
+    /// [`DefaultBodyLimit`](crate::extractor::DefaultBodyLimit), either
+    /// according to its `Content-Length` or once read.
+    LengthLimitExceeded { limit: usize },
+    /// The body couldn't be read from the connection.
+    FailedToBufferBody(Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl BodyRejection {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::LengthLimitExceeded { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+            Self::FailedToBufferBody(_) => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+impl fmt::Display for BodyRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::LengthLimitExceeded { limit } => {
+                write!(f, "Request body is larger than the limit of {limit} bytes")
+            }
+            Self::FailedToBufferBody(err) => write!(f, "Failed to buffer the request body: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for BodyRejection {}
+
+impl IntoMiniResponse for BodyRejection {
+    fn into_response(self) -> MiniResponse {
+        plain_text(self.status(), self.to_string())
+    }
+}
+
+/// Rejection used by [`Json`](crate::response::Json) when the request can't
+/// be read as JSON.
+#[derive(Debug)]
+pub enum JsonRejection {
+    /// The request didn't have an `application/json` content type.
+    MissingJsonContentType,
+    /// The body couldn't be read or was too large.
+    FailedToBufferBody(BodyRejection),
+    /// The body wasn't syntactically valid JSON.
+    JsonSyntaxError(serde_json::Error),
+    /// The body was valid JSON but didn't match the target type.
+    JsonDataError(serde_json::Error),
+}
+
+impl JsonRejection {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::MissingJsonContentType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            Self::FailedToBufferBody(err) => err.status(),
+            Self::JsonSyntaxError(_) => StatusCode::BAD_REQUEST,
+            Self::JsonDataError(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        }
+    }
+}
+
+impl fmt::Display for JsonRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingJsonContentType => {
+                f.write_str("Expected request with `Content-Type: application/json`")
+            }
+            Self::FailedToBufferBody(err) => err.fmt(f),
+            Self::JsonSyntaxError(err) => {
+                write!(f, "Failed to parse the request body as JSON: {err}")
+            }
+            Self::JsonDataError(err) => {
+                write!(
+                    f,
+                    "Failed to deserialize the JSON body into the target type: {err}"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for JsonRejection {}
+
+impl IntoMiniResponse for JsonRejection {
+    fn into_response(self) -> MiniResponse {
+        plain_text(self.status(), self.to_string())
+    }
+}
+
+/// Rejection used by [`Path`](crate::extractor::Path) when the captured
+/// parameters can't be turned into the target type.
+#[derive(Debug)]
+pub enum PathRejection {
+    /// The request wasn't dispatched by a [`Router`](crate::Router), so no
+    /// parameters were captured.
+    MissingPathParams,
+    /// A parameter wasn't valid UTF-8 once percent-decoded.
+    InvalidUtf8InPathParam { key: String },
+    /// The parameters couldn't be deserialized into the target type.
+    FailedToDeserializePathParams(PathDeserializationError),
+}
+
+impl PathRejection {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::MissingPathParams => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::InvalidUtf8InPathParam { .. } | Self::FailedToDeserializePathParams(_) => {
+                StatusCode::BAD_REQUEST
+            }
+        }
+    }
+}
+
+impl fmt::Display for PathRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingPathParams => f.write_str(
+                "No path parameters found for matched route. This is a bug in the application",
+            ),
+            Self::InvalidUtf8InPathParam { key } => {
+                write!(f, "Invalid UTF-8 in path parameter `{key}`")
+            }
+            Self::FailedToDeserializePathParams(err) => {
+                write!(f, "Invalid URL: {err}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PathRejection {}
+
+impl IntoMiniResponse for PathRejection {
+    fn into_response(self) -> MiniResponse {
+        plain_text(self.status(), self.to_string())
+    }
+}
+
+/// Rejection used by [`ConnectInfo`](crate::extractor::ConnectInfo) when no
+/// peer address of the requested type was recorded.
+#[derive(Debug)]
+pub enum ConnectInfoRejection {
+    /// The listener's address type doesn't match the extracted type, or the
+    /// request wasn't served by a [`Service`](crate::Service).
+    MissingConnectInfo,
+}
+
+impl ConnectInfoRejection {
+    pub fn status(&self) -> StatusCode {
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
+
+impl fmt::Display for ConnectInfoRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingConnectInfo => f.write_str(
+                "Missing connection info for the request. Is the address type the listener's?",
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ConnectInfoRejection {}
+
+impl IntoMiniResponse for ConnectInfoRejection {
+    fn into_response(self) -> MiniResponse {
+        plain_text(self.status(), self.to_string())
+    }
+}
+
+/// Rejection used by [`MatchedPath`](crate::extractor::MatchedPath) when the
+/// request didn't match a route.
+#[derive(Debug)]
+pub enum MatchedPathRejection {
+    /// The request was handled by a fallback, or not routed by a
+    /// [`Router`](crate::Router).
+    MatchedPathMissing,
+}
+
+impl MatchedPathRejection {
+    pub fn status(&self) -> StatusCode {
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
+
+impl fmt::Display for MatchedPathRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MatchedPathMissing => f.write_str("No matched path found for the request"),
+        }
+    }
+}
+
+impl std::error::Error for MatchedPathRejection {}
+
+impl IntoMiniResponse for MatchedPathRejection {
+    fn into_response(self) -> MiniResponse {
+        plain_text(self.status(), self.to_string())
+    }
+}
+
+/// Rejection used by [`Query`](crate::extractor::Query) when the query string
+/// can't be deserialized into the target type.
+#[derive(Debug)]
+pub enum QueryRejection {
+    FailedToDeserializeQueryString(serde_path_to_error::Error<serde_html_form::de::Error>),
+}
+
+impl QueryRejection {
+    pub fn status(&self) -> StatusCode {
+        StatusCode::BAD_REQUEST
+    }
+
+    /// The name of the field that failed to deserialize, if the error can be
+    /// pinned to a single field.
+    pub fn field(&self) -> Option<String> {
+        match self {
+            Self::FailedToDeserializeQueryString(err) => {
+                let path = err.path().to_string();
+                (path != ".").then_some(path)
+            }
+        }
+    }
+}
+
+impl fmt::Display for QueryRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self, self.field()) {
+            (Self::FailedToDeserializeQueryString(err), Some(field)) => write!(
+                f,
+                "Failed to deserialize query string: `{field}`: {}",
+                err.inner()
+            ),
+            (Self::FailedToDeserializeQueryString(err), None) => {
+                write!(f, "Failed to deserialize query string: {}", err.inner())
+            }
+        }
+    }
+}
+
+impl std::error::Error for QueryRejection {}
+
+impl IntoMiniResponse for QueryRejection {
+    fn into_response(self) -> MiniResponse {
+        plain_text(self.status(), self.to_string())
+    }
+}
+
+/// Rejection used by [`Form`](crate::response::Form) when the request can't
+/// be read as a form.
+#[derive(Debug)]
+pub enum FormRejection {
+    /// The request didn't have an `application/x-www-form-urlencoded` content type.
+    InvalidFormContentType,
+    /// The body couldn't be read or was too large.
+    FailedToBufferBody(BodyRejection),
+    /// The query string of a `GET` or `HEAD` request didn't match the target type.
+    FailedToDeserializeForm(serde_path_to_error::Error<serde_html_form::de::Error>),
+    /// The request body didn't match the target type.
+    FailedToDeserializeFormBody(serde_path_to_error::Error<serde_html_form::de::Error>),
+}
+
+impl FormRejection {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::InvalidFormContentType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            Self::FailedToBufferBody(err) => err.status(),
+            Self::FailedToDeserializeForm(_) => StatusCode::BAD_REQUEST,
+            Self::FailedToDeserializeFormBody(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        }
+    }
+}
+
+impl fmt::Display for FormRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidFormContentType => f.write_str(
+                "Form requests must have `Content-Type: application/x-www-form-urlencoded`",
+            ),
+            Self::FailedToBufferBody(err) => err.fmt(f),
+            Self::FailedToDeserializeForm(err) | Self::FailedToDeserializeFormBody(err) => {
+                write!(f, "Failed to deserialize form: {err}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for FormRejection {}
+
+impl IntoMiniResponse for FormRejection {
+    fn into_response(self) -> MiniResponse {
+        plain_text(self.status(), self.to_string())
+    }
+}
+
+/// Rejection used by [`Multipart`](crate::multipart::Multipart) when the
+/// request isn't a valid `multipart/form-data` request.
+#[derive(Debug)]
+pub enum MultipartRejection {
+    /// The `Content-Type` header was missing, wasn't `multipart/form-data`
+    /// or didn't carry a boundary.
+    InvalidBoundary,
+    /// The `Content-Length` of the request was over the body limit.
+    FailedToBufferBody(BodyRejection),
+}
+
+impl MultipartRejection {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::InvalidBoundary => StatusCode::BAD_REQUEST,
+            Self::FailedToBufferBody(err) => err.status(),
+        }
+    }
+}
+
+impl fmt::Display for MultipartRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidBoundary => {
+                f.write_str("Invalid `boundary` for `multipart/form-data` request")
+            }
+            Self::FailedToBufferBody(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for MultipartRejection {}
+
+impl IntoMiniResponse for MultipartRejection {
+    fn into_response(self) -> MiniResponse {
+        plain_text(self.status(), self.to_string())
+    }
+}
+
+/// Rejection used by [`WebSocketUpgrade`](crate::ws::WebSocketUpgrade) when
+/// the request isn't a valid WebSocket handshake.
+#[derive(Debug)]
+pub enum WebSocketUpgradeRejection {
+    /// The handshake wasn't a `GET` request.
+    MethodNotGet,
+    /// The `Connection` header didn't contain `upgrade`.
+    InvalidConnectionHeader,
+    /// The `Upgrade` header didn't contain `websocket`.
+    InvalidUpgradeHeader,
+    /// The `Sec-WebSocket-Version` header wasn't `13`.
+    InvalidWebSocketVersionHeader,
+    /// The `Sec-WebSocket-Key` header was missing.
+    WebSocketKeyHeaderMissing,
+    /// The connection can't be upgraded, for example because the request
+    /// wasn't served by hyper directly.
+    ConnectionNotUpgradable,
+}
+
+impl WebSocketUpgradeRejection {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::MethodNotGet => StatusCode::METHOD_NOT_ALLOWED,
+            Self::ConnectionNotUpgradable => StatusCode::UPGRADE_REQUIRED,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+impl fmt::Display for WebSocketUpgradeRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MethodNotGet => f.write_str("WebSocket requests must be `GET` requests"),
+            Self::InvalidConnectionHeader => {
+                f.write_str("Connection header did not include `upgrade`")
+            }
+            Self::InvalidUpgradeHeader => {
+                f.write_str("`Upgrade` header did not include `websocket`")
+            }
+            Self::InvalidWebSocketVersionHeader => {
+                f.write_str("`Sec-WebSocket-Version` header did not include `13`")
+            }
+            Self::WebSocketKeyHeaderMissing => f.write_str("`Sec-WebSocket-Key` header missing"),
+            Self::ConnectionNotUpgradable => f.write_str(
+                "WebSocket request couldn't be upgraded since no upgrade state was present",
+            ),
+        }
+    }
+}
+
+impl std::error::Error for WebSocketUpgradeRejection {}
+
+impl IntoMiniResponse for WebSocketUpgradeRejection {
+    fn into_response(self) -> MiniResponse {
+        plain_text(self.status(), self.to_string())
+    }
+}
@@ -1,47 +1,114 @@
+use std::borrow::Cow;
+
 use serde::{Deserialize, Serialize};
 
-use http_body_util::Full;
+use http::HeaderMap;
+use http::header::{CONTENT_TYPE, HeaderValue};
 use hyper::body::Bytes;
 use hyper::{Response, StatusCode};
 
+use crate::body::Body;
+
 pub trait IntoMiniResponse {
     fn into_response(self) -> MiniResponse;
 }
 
 pub struct MiniResponse {
     code: StatusCode,
-    content_type: String,
-    bytes: Bytes,
+    headers: HeaderMap,
+    body: Body,
 }
 
 impl MiniResponse {
-    fn new(code: StatusCode, content_type: &str, bytes: Bytes) -> Self {
+    pub(crate) fn new(code: StatusCode, content_type: &'static str, body: impl Into<Body>) -> Self {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+
         Self {
             code,
-            content_type: content_type.to_string(),
-            bytes,
+            headers,
+            body: body.into(),
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        self.code
+    }
+
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    pub fn headers_mut(&mut self) -> &mut HeaderMap {
+        &mut self.headers
+    }
+
+    pub fn hyper_response(self) -> Response<Body> {
+        let mut response = Response::new(self.body);
+        *response.status_mut() = self.code;
+        *response.headers_mut() = self.headers;
+
+        response
+    }
+}
+
+impl IntoMiniResponse for MiniResponse {
+    fn into_response(self) -> MiniResponse {
+        self
+    }
+}
+
+/// Sends the body as `application/octet-stream`, streaming it if it was
+/// created with [`Body::from_stream`]. Set a different `Content-Type` through
+/// [`MiniResponse::headers_mut`].
+impl IntoMiniResponse for Body {
+    fn into_response(self) -> MiniResponse {
+        MiniResponse::new(StatusCode::OK, "application/octet-stream", self)
+    }
+}
+
+impl IntoMiniResponse for StatusCode {
+    fn into_response(self) -> MiniResponse {
+        MiniResponse {
+            code: self,
+            headers: HeaderMap::new(),
+            body: Body::empty(),
         }
     }
+}
 
-    pub fn hyper_response(self) -> Response<Full<Bytes>> {
-        Response::builder()
-            .status(self.code)
-            .header("Content-Type", self.content_type)
-            .body(Full::new(self.bytes))
-            .unwrap()
+impl IntoMiniResponse for Response<Body> {
+    fn into_response(self) -> MiniResponse {
+        let (parts, body) = self.into_parts();
+
+        MiniResponse {
+            code: parts.status,
+            headers: parts.headers,
+            body,
+        }
     }
 }
 
 #[derive(Deserialize, Serialize)]
 pub struct Json<T>(pub T);
 
+impl<T> Json<T>
+where
+    T: Serialize,
+{
+    /// Serializes the wrapped value as compact JSON.
+    pub(crate) fn to_bytes(&self) -> serde_json::Result<Bytes> {
+        serde_json::to_vec(&self.0).map(Bytes::from)
+    }
+}
+
 impl<T> IntoMiniResponse for (StatusCode, Json<T>)
 where
     T: Serialize,
 {
     fn into_response(self) -> MiniResponse {
-        let (code, bytes) = self;
-        let bytes = Bytes::from(serde_json::to_vec(&bytes.0).unwrap());
+        let (code, json) = self;
+        let bytes = json.to_bytes().unwrap();
 
         MiniResponse::new(code, "application/json", bytes)
     }
@@ -52,27 +119,66 @@ where
     T: Serialize,
 {
     fn into_response(self) -> MiniResponse {
-        let bytes = Bytes::from(serde_json::to_vec(&self.0).unwrap());
+        let bytes = self.to_bytes().unwrap();
 
         MiniResponse::new(StatusCode::OK, "application/json", bytes)
     }
 }
 
-impl IntoMiniResponse for (StatusCode, &'static str) {
-    fn into_response(self) -> MiniResponse {
-        let (code, bytes) = self;
-        let bytes = Bytes::from(serde_json::to_vec(&bytes).unwrap());
+/// A `application/x-www-form-urlencoded` form. As an extractor it reads the
+/// query string for `GET` and `HEAD` requests and the body otherwise; as a
+/// response it serializes `T` into the body.
+pub struct Form<T>(pub T);
 
-        MiniResponse::new(code, "text/plain", bytes)
+impl<T> IntoMiniResponse for Form<T>
+where
+    T: Serialize,
+{
+    fn into_response(self) -> MiniResponse {
+        match serde_html_form::to_string(&self.0) {
+            Ok(body) => MiniResponse::new(
+                StatusCode::OK,
+                "application/x-www-form-urlencoded",
+                Bytes::from(body),
+            ),
+            Err(err) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to serialize form: {err}"),
+            )
+                .into_response(),
+        }
     }
 }
 
-impl IntoMiniResponse for &'static str {
-    fn into_response(self) -> MiniResponse {
-        let bytes = Bytes::from(serde_json::to_vec(&self).unwrap());
+macro_rules! impl_into_response_for_text {
+    ($($ty:ty => |$text:ident| $bytes:expr),* $(,)?) => {
+        $(
+            impl IntoMiniResponse for (StatusCode, $ty) {
+                fn into_response(self) -> MiniResponse {
+                    let (code, $text) = self;
+                    let bytes: Bytes = $bytes;
 
-        MiniResponse::new(StatusCode::OK, "text/plain", bytes)
-    }
+                    MiniResponse::new(code, "text/plain; charset=utf-8", bytes)
+                }
+            }
+
+            impl IntoMiniResponse for $ty {
+                fn into_response(self) -> MiniResponse {
+                    (StatusCode::OK, self).into_response()
+                }
+            }
+        )*
+    };
+}
+
+impl_into_response_for_text! {
+    &'static str => |text| Bytes::from_static(text.as_bytes()),
+    String => |text| Bytes::from(text),
+    Cow<'static, str> => |text| match text {
+        Cow::Borrowed(text) => Bytes::from_static(text.as_bytes()),
+        Cow::Owned(text) => Bytes::from(text),
+    },
+    Box<str> => |text| Bytes::from(String::from(text)),
 }
 
 impl<T, E> IntoMiniResponse for Result<T, E>
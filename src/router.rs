@@ -1,24 +1,219 @@
-use std::any::Any;
-use std::collections::HashMap;
 use std::pin::Pin;
-use std::sync::{Arc, RwLock};
+use std::sync::Arc;
+use std::task::{Context, Poll};
 
-use futures::FutureExt;
-use futures::future::BoxFuture;
-use http_body_util::Full;
 use hyper::body::{Bytes, Incoming};
-// use hyper::service::Service;
-use hyper::{Request, Response, StatusCode};
-use tower::Service;
-use tower::util::{BoxCloneService, BoxCloneSyncService};
-use tower::{Layer, ServiceBuilder};
+use hyper::{Method, Request, Response, StatusCode, Uri};
+use tower::Layer;
+use tower::util::BoxCloneSyncService;
+use tower::{Service, ServiceExt};
 
-use crate::endpoint::{HandlerService, IntoHandler, IntoHandlerStruct};
+use crate::body::{Body, BoxError};
+use crate::endpoint::Handler;
+use crate::extractor::{MatchedPath, OriginalUri};
+use crate::matcher::{Matcher, Params};
+use crate::method_router::{MethodNotAllowedFallback, MethodRouter, MethodService};
 use crate::response::IntoMiniResponse;
 
-#[derive(Clone, Default)]
+/// The name of the wildcard capturing the path below a nested router.
+const NEST_TAIL_PARAM: &str = "__private__mini_axum_nest_tail";
+
+/// What a route of the [`Matcher`] dispatches to.
+#[derive(Clone)]
+enum Endpoint {
+    /// The handlers of a route, and the service wrapping them with the
+    /// router's layers.
+    Route {
+        methods: MethodService,
+        service: DynService,
+    },
+    /// A router or service mounted with `nest` or `nest_service`, which gets
+    /// requests with the nesting prefix stripped from their path.
+    Nest {
+        service: DynService,
+        /// The routes of a nested router, `None` for a nested service.
+        routes: Option<Arc<Matcher<Endpoint>>>,
+    },
+}
+
+impl Matcher<Endpoint> {
+    /// Registers `methods` for `route`, combining them with the methods of a
+    /// route registered before, and wraps them with `layers`.
+    ///
+    /// # Panics
+    ///
+    /// Panics on malformed or conflicting routes and on methods handled twice.
+    fn add_route(&mut self, route: &str, methods: MethodService, layers: &[RecordedLayer]) {
+        match self.get_mut(route) {
+            Some(Endpoint::Route {
+                methods: existing,
+                service,
+            }) => {
+                if let Err(method) = existing.merge(methods) {
+                    panic!(
+                        "Overlapping method route: a handler for `{method} {route}` is already registered"
+                    );
+                }
+                *service = route_service(existing, layers);
+            }
+            _ => {
+                let service = route_service(&methods, layers);
+                self.add(route, Endpoint::Route { methods, service });
+            }
+        }
+    }
+
+    /// Whether a request for `path` with `method` reaches a route handler,
+    /// rather than a fallback or a `405 Method Not Allowed` response.
+    fn handles(&self, path: &str, method: &Method) -> bool {
+        let Some(mut matched) = self.at(path) else {
+            return false;
+        };
+
+        match matched.value {
+            Endpoint::Route { methods, .. } => methods.handles(method),
+            Endpoint::Nest {
+                routes: Some(routes),
+                ..
+            } => {
+                let tail = matched.params.take(NEST_TAIL_PARAM).unwrap_or_default();
+                routes.handles(&format!("/{tail}"), method)
+            }
+            Endpoint::Nest { routes: None, .. } => true,
+        }
+    }
+
+    /// Registers `endpoint` for `route`.
+    ///
+    /// # Panics
+    ///
+    /// Panics on malformed routes and if `route` is already registered.
+    fn add(&mut self, route: &str, endpoint: Endpoint) {
+        if self.get_mut(route).is_some() {
+            panic!("Route `{route}` conflicts with a router or service nested at the same path")
+        }
+        if let Err(err) = self.insert(route, endpoint) {
+            panic!("{err}");
+        }
+    }
+}
+
+/// Wraps the handlers of a route with `layers`.
+fn route_service(methods: &MethodService, layers: &[RecordedLayer]) -> DynService {
+    let routes = Routes::Methods(methods.clone());
+
+    layers.iter().fold(
+        BoxCloneSyncService::new(methods.clone()),
+        |service, recorded| recorded.wrap(Some(&routes), service),
+    )
+}
+
+/// Wraps a nested router or service with `layers`.
+fn nested_service(
+    service: DynService,
+    routes: Option<&Arc<Matcher<Endpoint>>>,
+    layers: &[RecordedLayer],
+) -> DynService {
+    let routes = routes.cloned().map(Routes::Nested);
+
+    layers.iter().fold(service, |service, recorded| {
+        recorded.wrap(routes.as_ref(), service)
+    })
+}
+
+/// The fallback of the closest router a request was nested through, used by
+/// nested routers without a fallback of their own.
+#[derive(Clone)]
+struct Fallback(DynService);
+
+/// A fallback registered on a router, with and without the router's own
+/// layers. Routes and nested routers get it without them, since requests only
+/// reach them through the layers already.
+#[derive(Clone)]
+struct RouterFallback {
+    service: DynService,
+    inherited: DynService,
+}
+
+/// A layer added with `layer` or `route_layer`. It's kept so services
+/// registered afterwards are wrapped the same way.
+#[derive(Clone)]
+struct RecordedLayer {
+    layer: Arc<dyn Fn(DynService) -> DynService + Send + Sync>,
+    /// Whether fallbacks are left unwrapped, as with `route_layer`.
+    route_only: bool,
+}
+
+impl RecordedLayer {
+    /// Wraps the service of an endpoint. Route layers only wrap the requests
+    /// `routes` handles, or every request without `routes`.
+    fn wrap(&self, routes: Option<&Routes>, service: DynService) -> DynService {
+        let layered = (self.layer)(service.clone());
+
+        match (self.route_only, routes) {
+            (true, Some(routes)) => BoxCloneSyncService::new(RouteOnly {
+                routes: routes.clone(),
+                layered,
+                inner: service,
+            }),
+            _ => layered,
+        }
+    }
+}
+
+/// The handlers behind an endpoint, telling which requests a route handler
+/// answers.
+#[derive(Clone)]
+enum Routes {
+    Methods(MethodService),
+    Nested(Arc<Matcher<Endpoint>>),
+}
+
+impl Routes {
+    fn handle(&self, req: &Request<Incoming>) -> bool {
+        match self {
+            Routes::Methods(methods) => methods.handles(req.method()),
+            Routes::Nested(routes) => routes.handles(req.uri().path(), req.method()),
+        }
+    }
+}
+
+/// An endpoint wrapped with a route layer. Requests none of its handlers
+/// answer skip the layer, so `405 Method Not Allowed`, automatic `OPTIONS` and
+/// nested fallback responses are left alone like fallbacks.
+#[derive(Clone)]
+struct RouteOnly {
+    routes: Routes,
+    layered: DynService,
+    inner: DynService,
+}
+
+impl Service<Request<Incoming>> for RouteOnly {
+    type Response = Response<Body>;
+    type Error = hyper::Error;
+    type Future = <DynService as Service<Request<Incoming>>>::Future;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Request<Incoming>) -> Self::Future {
+        match self.routes.handle(&req) {
+            true => self.layered.call(req),
+            false => self.inner.call(req),
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct Router<S = ()> {
-    pub inner: Arc<RwLock<HashMap<String, DynService>>>,
+    inner: Arc<Matcher<Endpoint>>,
+    fallback: Option<RouterFallback>,
+    /// Used when neither this router nor the ones it's nested in have a
+    /// fallback. It's a service so layers apply to it like to a fallback.
+    not_found: DynService,
+    method_not_allowed_fallback: Option<RouterFallback>,
+    layers: Vec<RecordedLayer>,
     state: S,
 }
 
@@ -28,74 +223,376 @@ where
 {
     pub fn with_state(state: S) -> Self {
         Self {
-            inner: Arc::new(RwLock::new(HashMap::new())),
+            inner: Arc::new(Matcher::default()),
+            fallback: None,
+            not_found: BoxCloneSyncService::new(tower::service_fn(|_req| async {
+                Ok((StatusCode::NOT_FOUND, "Not found")
+                    .into_response()
+                    .hyper_response())
+            })),
+            method_not_allowed_fallback: None,
+            layers: Vec::new(),
             state,
         }
     }
 
-    pub fn route<T, E>(self, route: &str, endpoint: E) -> Self
+    /// Registers `method_router` for `route`. Registering the same route again
+    /// adds the new methods to the existing ones. Segments wrapped in braces
+    /// capture a path parameter (`/users/{id}`), while a trailing `{*name}`
+    /// segment captures the rest of the path, which can't be empty. Captured
+    /// values can be read with [`Path`](crate::extractor::Path).
+    ///
+    /// Static segments take precedence over parameters, which take precedence
+    /// over wildcards.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the route is malformed, if a method is registered twice for
+    /// the same route, or if the route conflicts with an existing route,
+    /// e.g. `/users/{id}` and `/users/{name}`.
+    pub fn route(mut self, route: &str, method_router: MethodRouter<S>) -> Self {
+        let service = method_router.into_service(self.state.clone());
+        Arc::make_mut(&mut self.inner).add_route(route, service, &self.layers);
+
+        self
+    }
+
+    /// Adds every route of `other` to this router. Each router keeps the
+    /// state it was built with, and methods registered for the same route in
+    /// both are combined. The routes of `other` are wrapped with the layers
+    /// of this router on top of their own.
+    ///
+    /// ```no_run
+    /// # use mini_axum::{Router, method_router::get};
+    /// # async fn list_users() -> &'static str { "" }
+    /// # async fn health() -> &'static str { "" }
+    /// # let db = ();
+    /// let app = Router::with_state(db)
+    ///     .route("/users", get(list_users))
+    ///     .merge(Router::stateless().route("/health", get(health)));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if both routers handle the same method on the same route, if
+    /// both have a fallback of the same kind, or if a route of `other`
+    /// conflicts with one of this router, like in [`route`](Self::route).
+    pub fn merge<T>(mut self, other: Router<T>) -> Self {
+        let inner = Arc::make_mut(&mut self.inner);
+        for (route, endpoint) in other.inner.iter() {
+            // The handlers of `other` are reached through its own layers.
+            match endpoint {
+                Endpoint::Route { methods, service } => {
+                    inner.add_route(route, methods.routed_to(service.clone()), &self.layers);
+                }
+                Endpoint::Nest { service, routes } => {
+                    let service = nested_service(service.clone(), routes.as_ref(), &self.layers);
+                    let routes = routes.clone();
+                    inner.add(route, Endpoint::Nest { service, routes });
+                }
+            }
+        }
+
+        let other_fallback = other
+            .fallback
+            .map(|fallback| self.merged_fallback(fallback));
+        self.fallback = match (self.fallback, other_fallback) {
+            (Some(_), Some(_)) => panic!("Cannot merge two routers that both have a fallback"),
+            (fallback, other) => fallback.or(other),
+        };
+
+        let other_fallback = other
+            .method_not_allowed_fallback
+            .map(|fallback| self.merged_fallback(fallback));
+        self.method_not_allowed_fallback = match (self.method_not_allowed_fallback, other_fallback)
+        {
+            (Some(_), Some(_)) => {
+                panic!("Cannot merge two routers that both have a method not allowed fallback")
+            }
+            (fallback, other) => fallback.or(other),
+        };
+
+        self
+    }
+
+    /// Handles requests that don't match any route with `handler` instead of
+    /// the default `404 Not Found`. It takes extractors like any other handler:
+    ///
+    /// ```no_run
+    /// # use hyper::{StatusCode, Uri};
+    /// # use mini_axum::{Router, method_router::get};
+    /// # use mini_axum::response::{IntoMiniResponse, Json};
+    /// # use serde_json::json;
+    /// # async fn index() -> &'static str { "" }
+    /// async fn not_found(uri: Uri) -> impl IntoMiniResponse {
+    ///     (StatusCode::NOT_FOUND, Json(json!({ "title": "Not found", "path": uri.path() })))
+    /// }
+    ///
+    /// let app = Router::stateless().route("/", get(index)).fallback(not_found);
+    /// ```
+    ///
+    /// Routers nested in this one without a fallback of their own use it too.
+    pub fn fallback<H, T>(mut self, handler: H) -> Self
+    where
+        H: Handler<T, S>,
+        T: 'static,
+    {
+        let service = BoxCloneSyncService::new(handler.with_state(self.state.clone()));
+        self.fallback = Some(self.router_fallback(service));
+        self
+    }
+
+    /// Handles requests whose path matches a route but whose method doesn't
+    /// with `handler` instead of the default `405 Method Not Allowed`. The
+    /// `Allow` header is still added to its response.
+    ///
+    /// Routers nested in this one without such a fallback of their own use
+    /// it too.
+    pub fn method_not_allowed_fallback<H, T>(mut self, handler: H) -> Self
     where
-        T: 'static + Sync + Send,
-        E: IntoHandler<T, S> + Clone + Send + Sync + 'static,
-        IntoHandlerStruct<E, T, S>: tower::Service<
-                Request<Incoming>,
-                Response = Response<Full<Bytes>>,
-                Error = hyper::Error,
-                Future = Pin<
-                    Box<dyn Future<Output = Result<Response<Full<Bytes>>, hyper::Error>> + Send>,
-                >,
-            > + 'static,
+        H: Handler<T, S>,
+        T: 'static,
     {
-        let endpoint = endpoint.into_handler(self.state.clone());
+        let service = BoxCloneSyncService::new(handler.with_state(self.state.clone()));
+        self.method_not_allowed_fallback = Some(self.router_fallback(service));
+        self
+    }
+
+    /// Mounts `router` under `path`, so `/api` nesting a router with a
+    /// `/users` route serves `/api/users`:
+    ///
+    /// ```no_run
+    /// # use mini_axum::{Router, method_router::get};
+    /// # async fn list_users() -> &'static str { "" }
+    /// # let db = ();
+    /// let api = Router::with_state(db).route("/users", get(list_users));
+    /// let app = Router::stateless().nest("/api", api);
+    /// ```
+    ///
+    /// The nested router sees request URIs without the prefix. The full URI
+    /// is still available through [`OriginalUri`]. Parameters captured by
+    /// `path`, such as `/teams/{team}`, are available to the nested router's
+    /// handlers along with their own.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `path` is `/`, contains a wildcard or conflicts with an
+    /// existing route.
+    pub fn nest<T>(self, path: &str, router: Router<T>) -> Self
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        let routes = router.inner.clone();
+        self.nest_endpoint(path, BoxCloneSyncService::new(router), Some(routes))
+    }
+
+    /// Mounts any `tower::Service` under `path`, like [`nest`](Self::nest).
+    /// The service is called for every request below `path` regardless of
+    /// its method, and errors it returns are turned into
+    /// `500 Internal Server Error` responses.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `path` is `/`, contains a wildcard or conflicts with an
+    /// existing route.
+    pub fn nest_service<T, B>(self, path: &str, service: T) -> Self
+    where
+        T: Service<Request<Incoming>, Response = Response<B>> + Clone + Send + Sync + 'static,
+        T::Error: Into<BoxError>,
+        T::Future: Send + 'static,
+        B: hyper::body::Body<Data = Bytes> + Send + 'static,
+        B::Error: Into<BoxError>,
+    {
+        let service = BoxCloneSyncService::new(tower::service_fn(move |req: Request<Incoming>| {
+            let service = service.clone();
+
+            async move {
+                let res = match service.oneshot(req).await {
+                    Ok(res) => res.map(Body::new),
+                    Err(err) => {
+                        let err: BoxError = err.into();
+                        (StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+                            .into_response()
+                            .hyper_response()
+                    }
+                };
 
-        self.inner
-            .write()
-            .unwrap()
-            .insert(route.to_string(), BoxCloneSyncService::new(endpoint));
+                Ok::<_, hyper::Error>(res)
+            }
+        }));
+
+        self.nest_endpoint(path, service, None)
+    }
+
+    fn nest_endpoint(
+        mut self,
+        path: &str,
+        service: DynService,
+        routes: Option<Arc<Matcher<Endpoint>>>,
+    ) -> Self {
+        let prefix = path.trim_end_matches('/');
+
+        if prefix.is_empty() {
+            panic!("Nesting at the root is not supported, routes must be registered directly");
+        }
+        if prefix.contains("{*") {
+            panic!("Invalid nesting path `{path}`: nesting paths can't contain wildcards");
+        }
+
+        let service = nested_service(service, routes.as_ref(), &self.layers);
+        let endpoint = Endpoint::Nest { service, routes };
+        let inner = Arc::make_mut(&mut self.inner);
+        inner.add(prefix, endpoint.clone());
+        inner.add(&format!("{prefix}/"), endpoint.clone());
+        inner.add(&format!("{prefix}/{{*{NEST_TAIL_PARAM}}}"), endpoint);
 
         self
     }
 
-    pub fn layer<L>(mut self, layer: L) -> Self
+    /// Wraps every route, nested service and fallback with `layer`, including
+    /// ones registered afterwards. Layers added later wrap the earlier ones.
+    /// `405 Method Not Allowed` and automatic `OPTIONS` responses of routes go
+    /// through it too.
+    pub fn layer<L>(self, layer: L) -> Self
+    where
+        L: Layer<DynService> + Clone + Send + Sync + 'static,
+        L::Service: Service<Request<Incoming>, Response = Response<Body>, Error = hyper::Error>
+            + Clone
+            + Send
+            + Sync
+            + 'static,
+        <L::Service as Service<Request<Incoming>>>::Future: Send + 'static,
+    {
+        self.record_layer(layer, false)
+    }
+
+    /// Wraps every route and nested service with `layer` like
+    /// [`layer`](Self::layer), but not the fallbacks nor the
+    /// `405 Method Not Allowed` and automatic `OPTIONS` responses of routes,
+    /// including the routes of nested routers. Middleware rejecting requests,
+    /// like authentication, then only runs for requests matching a route, and
+    /// unknown paths still get a `404 Not Found`.
+    pub fn route_layer<L>(self, layer: L) -> Self
+    where
+        L: Layer<DynService> + Clone + Send + Sync + 'static,
+        L::Service: Service<Request<Incoming>, Response = Response<Body>, Error = hyper::Error>
+            + Clone
+            + Send
+            + Sync
+            + 'static,
+        <L::Service as Service<Request<Incoming>>>::Future: Send + 'static,
+    {
+        self.record_layer(layer, true)
+    }
+
+    fn record_layer<L>(mut self, layer: L, route_only: bool) -> Self
     where
         L: Layer<DynService> + Clone + Send + Sync + 'static,
-        L::Service: Service<
-                Request<Incoming>,
-                Response = Response<Full<Bytes>>,
-                Error = hyper::Error,
-                Future = BoxFuture<'static, Result<Response<Full<Bytes>>, hyper::Error>>,
-            > + Clone
+        L::Service: Service<Request<Incoming>, Response = Response<Body>, Error = hyper::Error>
+            + Clone
             + Send
             + Sync
             + 'static,
         <L::Service as Service<Request<Incoming>>>::Future: Send + 'static,
     {
-        let res: HashMap<String, DynService> = self
-            .inner
-            .write()
-            .unwrap()
-            .clone()
-            .into_iter()
-            .map(|(k, v)| {
-                let service = ServiceBuilder::new().layer(layer.clone()).service(v);
+        let recorded = RecordedLayer {
+            layer: Arc::new(move |service| BoxCloneSyncService::new(layer.layer(service))),
+            route_only,
+        };
 
-                (k, BoxCloneSyncService::new(service))
-            })
-            .collect();
+        for endpoint in Arc::make_mut(&mut self.inner).values_mut() {
+            match endpoint {
+                Endpoint::Route { methods, service } => {
+                    let routes = Routes::Methods(methods.clone());
+                    *service = recorded.wrap(Some(&routes), service.clone());
+                }
+                Endpoint::Nest { service, routes } => {
+                    let routes = routes.clone().map(Routes::Nested);
+                    *service = recorded.wrap(routes.as_ref(), service.clone());
+                }
+            }
+        }
 
-        self.inner = Arc::new(RwLock::new(res));
+        if !route_only {
+            let fallbacks = self
+                .fallback
+                .iter_mut()
+                .chain(self.method_not_allowed_fallback.as_mut())
+                .map(|fallback| &mut fallback.service)
+                .chain(std::iter::once(&mut self.not_found));
 
+            for service in fallbacks {
+                *service = (recorded.layer)(service.clone());
+            }
+        }
+
+        self.layers.push(recorded);
         self
     }
+
+    /// Wraps a fallback with every recorded layer but the route layers.
+    fn layered_fallback(&self, service: DynService) -> DynService {
+        self.layers
+            .iter()
+            .filter(|recorded| !recorded.route_only)
+            .fold(service, |service, recorded| (recorded.layer)(service))
+    }
+
+    fn router_fallback(&self, service: DynService) -> RouterFallback {
+        RouterFallback {
+            service: self.layered_fallback(service.clone()),
+            inherited: service,
+        }
+    }
+
+    /// Takes over the fallback of a merged router, whose own layers stay
+    /// part of what nested routers inherit.
+    fn merged_fallback(&self, fallback: RouterFallback) -> RouterFallback {
+        self.router_fallback(fallback.service)
+    }
+}
+pub(crate) type DynService = BoxCloneSyncService<Request<Incoming>, Response<Body>, hyper::Error>;
+
+impl<S> Default for Router<S>
+where
+    S: Clone + Default + Send + Sync + 'static,
+{
+    fn default() -> Self {
+        Self::with_state(S::default())
+    }
 }
-type DynService = BoxCloneSyncService<Request<Incoming>, Response<Full<Bytes>>, hyper::Error>;
 
 impl Router<()> {
     pub fn stateless() -> Self {
-        Self {
-            inner: Arc::new(RwLock::new(HashMap::new())),
-            state: (),
+        Self::with_state(())
+    }
+}
+
+/// Replaces the path of `uri` with `/{tail}`, keeping its query string.
+fn strip_nest_prefix(uri: &Uri, tail: &str) -> Uri {
+    let path_and_query = match uri.query() {
+        Some(query) => format!("/{tail}?{query}"),
+        None => format!("/{tail}"),
+    };
+
+    let mut parts = uri.clone().into_parts();
+    parts.path_and_query = Some(
+        path_and_query
+            .parse()
+            .expect("a suffix of a valid path is a valid path"),
+    );
+
+    Uri::from_parts(parts).expect("only the path of a valid URI was replaced")
+}
+
+/// Appends `route` to the path matched by the routers this one is nested in.
+fn join_matched_path(outer: Option<&MatchedPath>, route: &str) -> MatchedPath {
+    match outer {
+        Some(outer) if route == "/" => outer.clone(),
+        Some(outer) => {
+            MatchedPath(format!("{}{route}", outer.as_str().trim_end_matches('/')).into())
         }
+        None => MatchedPath(route.into()),
     }
 }
 
@@ -103,24 +600,98 @@ impl<S> hyper::service::Service<Request<Incoming>> for Router<S>
 where
     S: Clone + Send + Sync + 'static,
 {
-    type Response = Response<Full<Bytes>>;
+    type Response = Response<Body>;
     type Error = hyper::Error;
     type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
 
-    fn call(&self, req: Request<Incoming>) -> Self::Future {
-        let rdr = self.inner.read().unwrap();
-        let path = req.uri().path();
+    fn call(&self, mut req: Request<Incoming>) -> Self::Future {
+        let path = req.uri().path().to_string();
 
-        println!("Path: {path}");
-        if let Some(func) = rdr.get(req.uri().path()) {
-            let mut func = func.clone();
-            Box::pin(async move { func.call(req).await })
-        } else {
-            Box::pin(async move {
-                Ok((StatusCode::NOT_FOUND, "Not found")
-                    .into_response()
-                    .hyper_response())
-            })
+        if req.extensions().get::<OriginalUri>().is_none() {
+            let original = OriginalUri(req.uri().clone());
+            req.extensions_mut().insert(original);
         }
+
+        // Set by the router this one is nested in, to the prefix it matched.
+        let outer_path = req.extensions_mut().remove::<MatchedPath>();
+
+        let Some(matched) = self.inner.at(&path) else {
+            let fallback = self
+                .fallback
+                .as_ref()
+                .map(|fallback| fallback.service.clone());
+            let fallback = fallback.or_else(|| {
+                req.extensions()
+                    .get::<Fallback>()
+                    .map(|Fallback(fallback)| fallback.clone())
+            });
+
+            let mut fallback = fallback.unwrap_or_else(|| self.not_found.clone());
+            return Box::pin(async move { fallback.call(req).await });
+        };
+
+        if let Some(fallback) = &self.method_not_allowed_fallback {
+            req.extensions_mut()
+                .insert(MethodNotAllowedFallback(fallback.inherited.clone()));
+        }
+
+        // Routers nested in this one see the parameters captured here first.
+        let mut params = req.extensions_mut().remove::<Params>().unwrap_or_default();
+        params.extend(matched.params);
+
+        let (mut func, matched_path) = match matched.value {
+            Endpoint::Route { service, .. } => {
+                let matched_path = join_matched_path(outer_path.as_ref(), matched.route);
+                req.extensions_mut().insert(matched_path.clone());
+
+                (service.clone(), Some(matched_path))
+            }
+            Endpoint::Nest { service, .. } => {
+                if let Some(fallback) = &self.fallback {
+                    req.extensions_mut()
+                        .insert(Fallback(fallback.inherited.clone()));
+                }
+
+                let prefix = matched
+                    .route
+                    .strip_suffix(&format!("/{{*{NEST_TAIL_PARAM}}}"))
+                    .unwrap_or(matched.route)
+                    .trim_end_matches('/');
+                req.extensions_mut()
+                    .insert(join_matched_path(outer_path.as_ref(), prefix));
+
+                let tail = params.take(NEST_TAIL_PARAM).unwrap_or_default();
+                *req.uri_mut() = strip_nest_prefix(req.uri(), &tail);
+
+                (service.clone(), None)
+            }
+        };
+
+        req.extensions_mut().insert(params);
+        Box::pin(async move {
+            let mut res = func.call(req).await?;
+            if let Some(matched_path) = matched_path {
+                res.extensions_mut().insert(matched_path);
+            }
+
+            Ok(res)
+        })
+    }
+}
+
+impl<S> Service<Request<Incoming>> for Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    type Response = Response<Body>;
+    type Error = hyper::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Request<Incoming>) -> Self::Future {
+        hyper::service::Service::call(&*self, req)
     }
 }
@@ -1,43 +1,394 @@
-use std::pin::Pin;
+use std::pin::{Pin, pin};
+use std::sync::Arc;
+use std::time::Duration;
 
 use tokio::net::TcpListener;
+use tokio::sync::watch;
+use tokio::task::JoinSet;
 
+use http::{Extensions, Request};
+use hyper::body::Incoming;
 use hyper::server::conn::http1;
-use hyper_util::rt::TokioIo;
+use hyper::service::service_fn;
+use hyper_util::rt::{TokioExecutor, TokioIo, TokioTimer};
+use hyper_util::server::conn::auto;
 
+use crate::extractor::ConnectInfo;
+use crate::listener::{Connected, IncomingStream, Listener};
 use crate::router::Router;
+#[cfg(feature = "tls")]
+use crate::tls::RustlsConfig;
 
-pub struct Service<S> {
-    tcp: TcpListener,
+type ShutdownSignal = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// How long clients get to complete the TLS handshake.
+#[cfg(feature = "tls")]
+const TLS_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long to wait before accepting connections again after an error.
+const ACCEPT_ERROR_DELAY: Duration = Duration::from_millis(100);
+
+/// Inserts the [`ConnectInfo`] of a connection into each of its requests.
+type InsertConnectInfo = Arc<dyn Fn(&mut Extensions) + Send + Sync>;
+
+fn make_connect_info<L, C>(stream: IncomingStream<'_, L>) -> InsertConnectInfo
+where
+    L: Listener,
+    C: for<'a> Connected<IncomingStream<'a, L>>,
+{
+    let info = C::connect_info(stream);
+
+    Arc::new(move |extensions| {
+        extensions.insert(ConnectInfo(info.clone()));
+    })
+}
+
+/// Serves a [`Router`] over a [`Listener`], a TCP listener by default. Each
+/// connection speaks HTTP/1.1
+/// or, when the client starts with the HTTP/2 preface, cleartext HTTP/2
+/// (h2c with prior knowledge).
+pub struct Service<S, L: Listener = TcpListener> {
+    listener: L,
     router: Router<S>,
+    builder: auto::Builder<TokioExecutor>,
+    connect_info: fn(IncomingStream<'_, L>) -> InsertConnectInfo,
+    shutdown: Option<ShutdownSignal>,
+    shutdown_timeout: Option<Duration>,
+    #[cfg(feature = "tls")]
+    tls: Option<RustlsConfig>,
 }
 
-impl<S> Service<S>
+impl<S, L> Service<S, L>
 where
     S: Clone + Send + Sync + 'static,
+    L: Listener,
 {
-    pub fn new(tcp: TcpListener, router: Router<S>) -> Self {
-        Self { tcp, router }
+    /// Serves `router` on `listener`, such as a `TcpListener` or, on Unix, a
+    /// `UnixListener` for a socket file.
+    pub fn new(listener: L, router: Router<S>) -> Self {
+        let mut builder = auto::Builder::new(TokioExecutor::new());
+        builder.http1().timer(TokioTimer::new());
+        builder.http2().timer(TokioTimer::new());
+
+        Self {
+            listener,
+            router,
+            builder,
+            connect_info: |stream| {
+                let addr = stream.remote_addr;
+                Arc::new(move |extensions| {
+                    extensions.insert(ConnectInfo(addr.clone()));
+                })
+            },
+            shutdown: None,
+            shutdown_timeout: None,
+            #[cfg(feature = "tls")]
+            tls: None,
+        }
+    }
+
+    /// Serves `router` over TLS, terminating every connection with the
+    /// certificate currently held by `config`. Connections that don't
+    /// complete the handshake within 10 seconds, or before a graceful
+    /// shutdown starts, are closed.
+    ///
+    /// ```no_run
+    /// # use mini_axum::{Router, Service, tls::RustlsConfig};
+    /// # async fn run(tcp: tokio::net::TcpListener, router: Router) -> Result<(), Box<dyn std::error::Error>> {
+    /// let config = RustlsConfig::from_pem_file("cert.pem", "key.pem")?;
+    /// Service::tls(tcp, router, config).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "tls")]
+    pub fn tls(listener: L, router: Router<S>, config: RustlsConfig) -> Self {
+        let mut svc = Self::new(listener, router);
+        svc.tls = Some(config);
+        svc
+    }
+
+    /// Makes `C` available through [`ConnectInfo`] instead of the peer
+    /// address returned by the listener.
+    ///
+    /// ```no_run
+    /// # #[cfg(unix)]
+    /// # mod example {
+    /// # use mini_axum::{Router, Service};
+    /// # use mini_axum::listener::{Connected, IncomingStream};
+    /// # use tokio::net::UnixListener;
+    /// # #[derive(Clone)]
+    /// # struct PeerCred(Option<u32>);
+    /// # impl Connected<IncomingStream<'_, UnixListener>> for PeerCred {
+    /// #     fn connect_info(stream: IncomingStream<'_, UnixListener>) -> Self {
+    /// #         PeerCred(stream.io().peer_cred().ok().map(|cred| cred.uid()))
+    /// #     }
+    /// # }
+    /// # fn run(unix_listener: UnixListener, router: Router) {
+    /// Service::new(unix_listener, router).connect_info::<PeerCred>()
+    /// # ;
+    /// # }
+    /// # }
+    /// ```
+    pub fn connect_info<C>(mut self) -> Self
+    where
+        C: for<'a> Connected<IncomingStream<'a, L>>,
+    {
+        self.connect_info = make_connect_info::<L, C>;
+        self
+    }
+
+    /// Shuts the server down once `signal` completes: no new connections are
+    /// accepted, and open connections finish the requests they're serving
+    /// before closing.
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use mini_axum::{Router, Service};
+    /// # async fn run(tcp: tokio::net::TcpListener, router: Router) -> Result<(), Box<dyn std::error::Error>> {
+    /// Service::new(tcp, router)
+    ///     .with_graceful_shutdown(async { tokio::signal::ctrl_c().await.unwrap() })
+    ///     .graceful_shutdown_timeout(Duration::from_secs(30))
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_graceful_shutdown<F>(mut self, signal: F) -> Self
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        self.shutdown = Some(Box::pin(signal));
+        self
+    }
+
+    /// How long to wait for open connections to drain after the shutdown
+    /// signal. Connections still open afterwards are closed, aborting the
+    /// requests they're serving. Without a timeout the server waits for every
+    /// connection.
+    pub fn graceful_shutdown_timeout(mut self, timeout: Duration) -> Self {
+        self.shutdown_timeout = Some(timeout);
+        self
+    }
+
+    /// Only serve HTTP/1.1, rejecting HTTP/2 connections.
+    pub fn http1_only(mut self) -> Self {
+        self.builder = self.builder.http1_only();
+        self
+    }
+
+    /// Only serve HTTP/2, rejecting HTTP/1.1 connections.
+    pub fn http2_only(mut self) -> Self {
+        self.builder = self.builder.http2_only();
+        self
+    }
+
+    /// The maximum number of concurrent streams per HTTP/2 connection, 200 by
+    /// default.
+    pub fn http2_max_concurrent_streams(mut self, max: u32) -> Self {
+        self.builder.http2().max_concurrent_streams(max);
+        self
+    }
+
+    /// The initial HTTP/2 flow control window of each stream, in bytes.
+    pub fn http2_initial_stream_window_size(mut self, size: u32) -> Self {
+        self.builder.http2().initial_stream_window_size(size);
+        self
+    }
+
+    /// The initial HTTP/2 flow control window of each connection, in bytes.
+    pub fn http2_initial_connection_window_size(mut self, size: u32) -> Self {
+        self.builder.http2().initial_connection_window_size(size);
+        self
+    }
+
+    /// Sizes the HTTP/2 flow control windows from the measured bandwidth,
+    /// overriding the initial window sizes.
+    pub fn http2_adaptive_window(mut self, enabled: bool) -> Self {
+        self.builder.http2().adaptive_window(enabled);
+        self
+    }
+
+    /// Sends an HTTP/2 ping every `interval` to keep idle connections alive.
+    /// Disabled by default.
+    pub fn http2_keep_alive_interval(mut self, interval: Duration) -> Self {
+        self.builder.http2().keep_alive_interval(interval);
+        self
+    }
+
+    /// How long to wait for a keep-alive ping to be acknowledged before
+    /// closing the connection, 20 seconds by default.
+    pub fn http2_keep_alive_timeout(mut self, timeout: Duration) -> Self {
+        self.builder.http2().keep_alive_timeout(timeout);
+        self
     }
 
     async fn run(self) -> Result<(), Box<dyn std::error::Error>> {
+        let Self {
+            mut listener,
+            router,
+            builder,
+            connect_info,
+            shutdown,
+            shutdown_timeout,
+            #[cfg(feature = "tls")]
+            tls,
+        } = self;
+
+        let mut shutdown = shutdown.unwrap_or_else(|| Box::pin(std::future::pending()));
+        let (signal_tx, signal_rx) = watch::channel(());
+        let mut connections = JoinSet::new();
+
         loop {
-            let (stream, _) = self.tcp.accept().await?;
-            let io = TokioIo::new(stream);
+            let (stream, addr) = tokio::select! {
+                conn = listener.accept() => match conn {
+                    Ok(conn) => conn,
+                    // Usually temporary, like running out of file
+                    // descriptors, so open connections keep being served.
+                    Err(err) => {
+                        tracing::error!(error = %err, "Error accepting connection");
+                        tokio::time::sleep(ACCEPT_ERROR_DELAY).await;
+                        continue;
+                    }
+                },
+                () = &mut shutdown => break,
+                // Reaps finished connections so the set doesn't keep growing.
+                Some(_) = connections.join_next(), if !connections.is_empty() => continue,
+            };
 
-            let rtr = self.router.clone();
-            tokio::task::spawn(async move {
-                if let Err(err) = http1::Builder::new().serve_connection(io, rtr).await {
-                    eprintln!("Error serving connection: {:?}", err);
-                }
+            let connect_info = connect_info(IncomingStream {
+                io: &stream,
+                remote_addr: addr,
             });
+            let rtr = router.clone();
+            let builder = builder.clone();
+            let signal = signal_rx.clone();
+
+            #[cfg(feature = "tls")]
+            if let Some(config) = &tls {
+                let acceptor = config.acceptor();
+                let mut signal = signal;
+                connections.spawn(async move {
+                    let handshake =
+                        tokio::time::timeout(TLS_HANDSHAKE_TIMEOUT, acceptor.accept(stream));
+                    let stream = tokio::select! {
+                        res = handshake => match res {
+                            Ok(Ok(stream)) => stream,
+                            Ok(Err(err)) => {
+                                tracing::debug!(error = %err, "TLS handshake failed");
+                                return;
+                            }
+                            Err(_) => {
+                                tracing::debug!("TLS handshake timed out");
+                                return;
+                            }
+                        },
+                        _ = signal.changed() => return,
+                    };
+
+                    serve_connection(builder, TokioIo::new(stream), connect_info, rtr, signal).await
+                });
+                continue;
+            }
+
+            connections.spawn(serve_connection(
+                builder,
+                TokioIo::new(stream),
+                connect_info,
+                rtr,
+                signal,
+            ));
+        }
+
+        drop(listener);
+        signal_tx.send_replace(());
+
+        let drain = async { while connections.join_next().await.is_some() {} };
+        match shutdown_timeout {
+            Some(timeout) => {
+                if tokio::time::timeout(timeout, drain).await.is_err() {
+                    tracing::warn!(
+                        connections = connections.len(),
+                        "Graceful shutdown timed out, closing the remaining connections"
+                    );
+                    connections.shutdown().await;
+                }
+            }
+            None => drain.await,
+        }
+
+        Ok(())
+    }
+}
+
+/// Serves a single connection until it closes, shutting it down gracefully
+/// once `signal` fires.
+async fn serve_connection<I, S>(
+    builder: auto::Builder<TokioExecutor>,
+    io: I,
+    connect_info: InsertConnectInfo,
+    router: Router<S>,
+    signal: watch::Receiver<()>,
+) where
+    I: hyper::rt::Read + hyper::rt::Write + Unpin + Send + 'static,
+    S: Clone + Send + Sync + 'static,
+{
+    let svc = service_fn(move |mut req: Request<Incoming>| {
+        connect_info(req.extensions_mut());
+        hyper::service::Service::call(&router, req)
+    });
+
+    // `serve_connection_with_upgrades` ignores `http1_only` and `http2_only`,
+    // so connections limited to one protocol are served without detection.
+    if !builder.is_http2_available() {
+        let mut http1 = http1::Builder::new();
+        http1.timer(TokioTimer::new());
+        let conn = pin!(http1.serve_connection(io, svc).with_upgrades());
+        drive(conn, |conn| conn.graceful_shutdown(), signal).await;
+    } else if !builder.is_http1_available() {
+        // HTTP/2 has no upgrades.
+        let conn = pin!(builder.serve_connection(io, svc));
+        drive(conn, |conn| conn.graceful_shutdown(), signal).await;
+    } else {
+        let conn = pin!(builder.serve_connection_with_upgrades(io, svc));
+        drive(conn, |conn| conn.graceful_shutdown(), signal).await;
+    }
+}
+
+/// Drives `conn` to completion, calling `graceful_shutdown` once `signal`
+/// fires.
+async fn drive<C, E>(
+    mut conn: Pin<&mut C>,
+    graceful_shutdown: fn(Pin<&mut C>),
+    mut signal: watch::Receiver<()>,
+) where
+    C: Future<Output = Result<(), E>>,
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    let (res, shutting_down) = tokio::select! {
+        res = conn.as_mut() => (res, false),
+        _ = signal.changed() => {
+            graceful_shutdown(conn.as_mut());
+            (conn.await, true)
         }
+    };
+
+    match res.map_err(Into::into) {
+        // Connections that hadn't sent a request yet are cancelled by the
+        // shutdown itself.
+        Err(err) if shutting_down && is_cancelled(&*err) => {}
+        Err(err) => tracing::debug!(error = %err, "Error serving connection"),
+        Ok(()) => {}
     }
 }
 
-impl<S> IntoFuture for Service<S>
+fn is_cancelled(err: &(dyn std::error::Error + 'static)) -> bool {
+    err.downcast_ref::<std::io::Error>()
+        .is_some_and(|err| err.kind() == std::io::ErrorKind::Interrupted)
+}
+
+impl<S, L> IntoFuture for Service<S, L>
 where
     S: Clone + Send + Sync + 'static,
+    L: Listener,
 {
     type Output = Result<(), Box<dyn std::error::Error>>;
     type IntoFuture = Pin<Box<dyn Future<Output = Self::Output> + Send>>;
@@ -0,0 +1,285 @@
+use std::fmt::Write;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures::Stream;
+use http::StatusCode;
+use http::header::{CACHE_CONTROL, HeaderValue};
+use hyper::body::Bytes;
+use serde::Serialize;
+use tokio::time::{Instant, Sleep};
+
+use crate::body::{Body, BoxError};
+use crate::response::{IntoMiniResponse, Json, MiniResponse};
+
+/// A `text/event-stream` response sending every [`Event`] of a stream as soon
+/// as it's produced.
+///
+/// ```no_run
+/// # use futures::StreamExt;
+/// # use mini_axum::response::IntoMiniResponse;
+/// # use mini_axum::sse::{Event, KeepAlive, Sse};
+/// async fn updates() -> impl IntoMiniResponse {
+///     let events = futures::stream::iter(0..10)
+///         .map(|i| Event::default().event("tick").json_data(i));
+///
+///     Sse::new(events).keep_alive(KeepAlive::default())
+/// }
+/// ```
+pub struct Sse<S> {
+    stream: S,
+    keep_alive: Option<KeepAlive>,
+}
+
+impl<S> Sse<S> {
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream,
+            keep_alive: None,
+        }
+    }
+
+    /// Sends a comment whenever no event was sent for a while, so proxies
+    /// don't close the idle connection.
+    pub fn keep_alive(mut self, keep_alive: KeepAlive) -> Self {
+        self.keep_alive = Some(keep_alive);
+        self
+    }
+}
+
+impl<S, E> IntoMiniResponse for Sse<S>
+where
+    S: Stream<Item = Result<Event, E>> + Send + 'static,
+    E: Into<BoxError> + 'static,
+{
+    fn into_response(self) -> MiniResponse {
+        let stream = SseStream {
+            events: Box::pin(self.stream),
+            keep_alive: self.keep_alive.map(|keep_alive| {
+                let sleep = Box::pin(tokio::time::sleep(keep_alive.interval));
+                (keep_alive, sleep)
+            }),
+        };
+
+        let mut res = MiniResponse::new(
+            StatusCode::OK,
+            "text/event-stream",
+            Body::from_stream(stream),
+        );
+        res.headers_mut()
+            .insert(CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+
+        res
+    }
+}
+
+struct SseStream<S> {
+    events: Pin<Box<S>>,
+    keep_alive: Option<(KeepAlive, Pin<Box<Sleep>>)>,
+}
+
+impl<S, E> Stream for SseStream<S>
+where
+    S: Stream<Item = Result<Event, E>>,
+{
+    type Item = Result<Bytes, E>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+
+        match this.events.as_mut().poll_next(cx) {
+            Poll::Ready(Some(event)) => {
+                if let Some((keep_alive, sleep)) = &mut this.keep_alive {
+                    sleep.as_mut().reset(Instant::now() + keep_alive.interval);
+                }
+
+                Poll::Ready(Some(event.map(Event::finalize)))
+            }
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => {
+                let Some((keep_alive, sleep)) = &mut this.keep_alive else {
+                    return Poll::Pending;
+                };
+
+                match sleep.as_mut().poll(cx) {
+                    Poll::Ready(()) => {
+                        sleep.as_mut().reset(Instant::now() + keep_alive.interval);
+                        Poll::Ready(Some(Ok(keep_alive.event.clone())))
+                    }
+                    Poll::Pending => Poll::Pending,
+                }
+            }
+        }
+    }
+}
+
+/// A single server-sent event. Every field is optional.
+#[derive(Clone, Debug, Default)]
+pub struct Event {
+    buffer: String,
+}
+
+impl Event {
+    /// Sets the `data` field. Text spanning several lines is sent as one
+    /// `data` line per line of text, with `\r\n`, `\r` and `\n` all ending a
+    /// line as they do for clients.
+    pub fn data(mut self, data: impl AsRef<str>) -> Self {
+        for line in data
+            .as_ref()
+            .split("\r\n")
+            .flat_map(|line| line.split(['\r', '\n']))
+        {
+            self.field("data", line);
+        }
+        self
+    }
+
+    /// Sets the `data` field to `data` serialized as JSON.
+    pub fn json_data<T: Serialize>(self, data: T) -> Result<Self, serde_json::Error> {
+        let bytes = Json(data).to_bytes()?;
+        let json = String::from_utf8(Vec::from(bytes)).expect("serde_json emits valid UTF-8");
+
+        Ok(self.data(json))
+    }
+
+    /// Sets the `event` field, the name listeners subscribe to.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `event` contains a newline or carriage return.
+    pub fn event(mut self, event: impl AsRef<str>) -> Self {
+        self.single_line_field("event", event.as_ref());
+        self
+    }
+
+    /// Sets the `id` field, sent back by clients as `Last-Event-ID` when
+    /// they reconnect.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` contains a newline or carriage return.
+    pub fn id(mut self, id: impl AsRef<str>) -> Self {
+        self.single_line_field("id", id.as_ref());
+        self
+    }
+
+    /// Sets the `retry` field, the time clients wait before reconnecting.
+    pub fn retry(mut self, retry: Duration) -> Self {
+        self.field("retry", &retry.as_millis().to_string());
+        self
+    }
+
+    /// Adds a comment line, which clients ignore.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `comment` contains a newline or carriage return.
+    pub fn comment(mut self, comment: impl AsRef<str>) -> Self {
+        self.single_line_field("", comment.as_ref());
+        self
+    }
+
+    fn single_line_field(&mut self, name: &str, value: &str) {
+        assert!(
+            !value.contains(['\n', '\r']),
+            "SSE field values cannot contain newlines or carriage returns"
+        );
+        self.field(name, value);
+    }
+
+    fn field(&mut self, name: &str, value: &str) {
+        let _ = writeln!(self.buffer, "{name}: {value}");
+    }
+
+    fn finalize(mut self) -> Bytes {
+        self.buffer.push('\n');
+        Bytes::from(self.buffer)
+    }
+}
+
+/// Configures the comments sent by [`Sse::keep_alive`]. By default a
+/// `: keep-alive` comment is sent after 15 seconds without events.
+#[derive(Clone, Debug)]
+pub struct KeepAlive {
+    interval: Duration,
+    event: Bytes,
+}
+
+impl KeepAlive {
+    pub fn new() -> Self {
+        Self {
+            interval: Duration::from_secs(15),
+            event: Event::default().comment("keep-alive").finalize(),
+        }
+    }
+
+    /// How long to wait without events before sending a keep-alive comment.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// The text of the keep-alive comment.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `text` contains a newline or carriage return.
+    pub fn text(mut self, text: impl AsRef<str>) -> Self {
+        self.event = Event::default().comment(text).finalize();
+        self
+    }
+}
+
+impl Default for KeepAlive {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(event: Event) -> String {
+        String::from_utf8(event.finalize().to_vec()).unwrap()
+    }
+
+    #[test]
+    fn sends_one_data_line_per_line_of_text() {
+        let event = Event::default().data("a\nb\r\nc\rd\n\re");
+
+        assert_eq!(
+            encode(event),
+            "data: a\ndata: b\ndata: c\ndata: d\ndata: \ndata: e\n\n"
+        );
+    }
+
+    #[test]
+    fn carriage_returns_cannot_inject_fields() {
+        let event = Event::default().data("x\rid: evil");
+
+        assert_eq!(encode(event), "data: x\ndata: id: evil\n\n");
+    }
+
+    #[test]
+    fn writes_every_field() {
+        let event = Event::default()
+            .event("tick")
+            .id("1")
+            .retry(Duration::from_secs(3))
+            .comment("hi")
+            .data("");
+
+        assert_eq!(
+            encode(event),
+            "event: tick\nid: 1\nretry: 3000\n: hi\ndata: \n\n"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "SSE field values cannot contain newlines")]
+    fn rejects_carriage_returns_in_single_line_fields() {
+        let _ = Event::default().id("1\rdata: evil");
+    }
+}
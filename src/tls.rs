@@ -0,0 +1,116 @@
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+use tokio_rustls::TlsAcceptor;
+use tokio_rustls::rustls::ServerConfig;
+use tokio_rustls::rustls::crypto::ring;
+use tokio_rustls::rustls::pki_types::pem::PemObject;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+
+/// The rustls configuration used by [`Service::tls`](crate::Service::tls).
+///
+/// Clones share the same configuration, so a certificate can be swapped out
+/// while the server is running. New connections pick up the reloaded
+/// certificate and open connections keep theirs:
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// # use mini_axum::{Router, Service, tls::RustlsConfig};
+/// # async fn run(tcp: tokio::net::TcpListener, router: Router) -> Result<(), Box<dyn std::error::Error>> {
+/// let config = RustlsConfig::from_pem_file("cert.pem", "key.pem")?;
+///
+/// tokio::spawn({
+///     let config = config.clone();
+///     async move {
+///         loop {
+///             tokio::time::sleep(Duration::from_secs(3600)).await;
+///             let _ = config.reload_from_pem_file("cert.pem", "key.pem");
+///         }
+///     }
+/// });
+///
+/// Service::tls(tcp, router, config).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct RustlsConfig {
+    inner: Arc<RwLock<Arc<ServerConfig>>>,
+}
+
+impl RustlsConfig {
+    /// Uses `config` as is. Its ALPN protocols should include `h2` for
+    /// clients to negotiate HTTP/2.
+    pub fn from_config(config: Arc<ServerConfig>) -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(config)),
+        }
+    }
+
+    /// Builds a configuration from a PEM certificate chain and private key,
+    /// advertising `h2` and `http/1.1` over ALPN.
+    pub fn from_pem(cert: &[u8], key: &[u8]) -> io::Result<Self> {
+        Ok(Self::from_config(config_from_pem(cert, key)?))
+    }
+
+    /// Builds a configuration from PEM files, like [`from_pem`](Self::from_pem).
+    pub fn from_pem_file(cert: impl AsRef<Path>, key: impl AsRef<Path>) -> io::Result<Self> {
+        Self::from_pem(&std::fs::read(cert)?, &std::fs::read(key)?)
+    }
+
+    /// Replaces the configuration used for new connections.
+    pub fn reload_from_config(&self, config: Arc<ServerConfig>) {
+        *self.inner.write().unwrap_or_else(|err| err.into_inner()) = config;
+    }
+
+    /// Replaces the certificate used for new connections. The current one is
+    /// kept if the new one can't be loaded.
+    pub fn reload_from_pem(&self, cert: &[u8], key: &[u8]) -> io::Result<()> {
+        self.reload_from_config(config_from_pem(cert, key)?);
+        Ok(())
+    }
+
+    /// Replaces the certificate used for new connections with the one in the
+    /// given PEM files.
+    pub fn reload_from_pem_file(
+        &self,
+        cert: impl AsRef<Path>,
+        key: impl AsRef<Path>,
+    ) -> io::Result<()> {
+        self.reload_from_pem(&std::fs::read(cert)?, &std::fs::read(key)?)
+    }
+
+    /// The configuration currently used for new connections.
+    pub fn get_inner(&self) -> Arc<ServerConfig> {
+        self.inner
+            .read()
+            .unwrap_or_else(|err| err.into_inner())
+            .clone()
+    }
+
+    pub(crate) fn acceptor(&self) -> TlsAcceptor {
+        TlsAcceptor::from(self.get_inner())
+    }
+}
+
+fn config_from_pem(cert: &[u8], key: &[u8]) -> io::Result<Arc<ServerConfig>> {
+    let certs = CertificateDer::pem_slice_iter(cert)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(invalid_data)?;
+    let key = PrivateKeyDer::from_pem_slice(key).map_err(invalid_data)?;
+
+    let mut config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .map_err(invalid_data)?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(invalid_data)?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+    Ok(Arc::new(config))
+}
+
+fn invalid_data(err: impl std::error::Error + Send + Sync + 'static) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
@@ -0,0 +1,370 @@
+use std::fmt;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use futures::{Sink, SinkExt, Stream, StreamExt};
+use http::header::{
+    CONNECTION, HeaderMap, HeaderValue, SEC_WEBSOCKET_ACCEPT, SEC_WEBSOCKET_KEY,
+    SEC_WEBSOCKET_PROTOCOL, SEC_WEBSOCKET_VERSION, UPGRADE,
+};
+use http::request::Parts;
+use http::{Method, Request, StatusCode};
+use hyper::body::{Bytes, Incoming};
+use hyper::upgrade::{OnUpgrade, Upgraded};
+use hyper_util::rt::TokioIo;
+use sha1::{Digest, Sha1};
+use tokio_tungstenite::WebSocketStream;
+use tokio_tungstenite::tungstenite::protocol::{
+    self, Role, WebSocketConfig, frame::coding::CloseCode,
+};
+
+use crate::extractor::{FromRequest, FromRequestParts};
+use crate::rejection::WebSocketUpgradeRejection;
+use crate::response::{IntoMiniResponse, MiniResponse};
+
+/// Extracts a WebSocket handshake, validating its headers. Call
+/// [`on_upgrade`](WebSocketUpgrade::on_upgrade) to accept it and return the
+/// resulting response from the handler.
+///
+/// ```no_run
+/// # use mini_axum::{response::IntoMiniResponse, ws::WebSocketUpgrade};
+/// async fn chat(ws: WebSocketUpgrade) -> impl IntoMiniResponse {
+///     ws.on_upgrade(|mut socket| async move {
+///         while let Some(Ok(msg)) = socket.recv().await {
+///             if socket.send(msg).await.is_err() {
+///                 break;
+///             }
+///         }
+///     })
+/// }
+/// ```
+pub struct WebSocketUpgrade {
+    on_upgrade: OnUpgrade,
+    key: HeaderValue,
+    requested_protocols: Option<HeaderValue>,
+    protocol: Option<HeaderValue>,
+    config: WebSocketConfig,
+}
+
+fn header_contains(headers: &HeaderMap, name: http::header::HeaderName, token: &str) -> bool {
+    headers
+        .get_all(name)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|value| value.trim().eq_ignore_ascii_case(token))
+}
+
+impl<S> FromRequestParts<S> for WebSocketUpgrade
+where
+    S: Clone + Send + Sync,
+{
+    type Rejection = WebSocketUpgradeRejection;
+
+    async fn from_request_parts(mut req: Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        if req.method != Method::GET {
+            return Err(WebSocketUpgradeRejection::MethodNotGet);
+        }
+
+        if !header_contains(&req.headers, CONNECTION, "upgrade") {
+            return Err(WebSocketUpgradeRejection::InvalidConnectionHeader);
+        }
+
+        if !header_contains(&req.headers, UPGRADE, "websocket") {
+            return Err(WebSocketUpgradeRejection::InvalidUpgradeHeader);
+        }
+
+        if req
+            .headers
+            .get(SEC_WEBSOCKET_VERSION)
+            .map(HeaderValue::as_bytes)
+            != Some(b"13")
+        {
+            return Err(WebSocketUpgradeRejection::InvalidWebSocketVersionHeader);
+        }
+
+        let key = req
+            .headers
+            .get(SEC_WEBSOCKET_KEY)
+            .cloned()
+            .ok_or(WebSocketUpgradeRejection::WebSocketKeyHeaderMissing)?;
+
+        let on_upgrade = req
+            .extensions
+            .remove::<OnUpgrade>()
+            .ok_or(WebSocketUpgradeRejection::ConnectionNotUpgradable)?;
+
+        Ok(Self {
+            on_upgrade,
+            key,
+            requested_protocols: req.headers.get(SEC_WEBSOCKET_PROTOCOL).cloned(),
+            protocol: None,
+            config: WebSocketConfig::default(),
+        })
+    }
+}
+
+impl<S> FromRequest<S> for WebSocketUpgrade
+where
+    S: Clone + Send + Sync,
+{
+    type Rejection = WebSocketUpgradeRejection;
+
+    async fn from_request(req: Request<Incoming>, state: &S) -> Result<Self, Self::Rejection> {
+        let (parts, _) = req.into_parts();
+
+        Self::from_request_parts(parts, state).await
+    }
+}
+
+impl WebSocketUpgrade {
+    /// Selects the first of `protocols` the client asked for in its
+    /// `Sec-WebSocket-Protocol` header.
+    pub fn protocols<I>(mut self, protocols: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let requested = self
+            .requested_protocols
+            .as_ref()
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default();
+
+        self.protocol = protocols
+            .into_iter()
+            .find(|protocol| {
+                requested
+                    .split(',')
+                    .any(|requested| requested.trim() == protocol.as_ref())
+            })
+            .and_then(|protocol| HeaderValue::from_str(protocol.as_ref()).ok());
+
+        self
+    }
+
+    /// The maximum size of an incoming message, 64 MiB by default.
+    pub fn max_message_size(mut self, max: usize) -> Self {
+        self.config = self.config.max_message_size(Some(max));
+        self
+    }
+
+    /// Accepts the handshake. Once the `101 Switching Protocols` response has
+    /// been sent, `callback` runs in its own task with the upgraded socket.
+    pub fn on_upgrade<F, Fut>(self, callback: F) -> MiniResponse
+    where
+        F: FnOnce(WebSocket) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let Self {
+            on_upgrade,
+            key,
+            protocol,
+            config,
+            ..
+        } = self;
+
+        tokio::spawn(async move {
+            let Ok(upgraded) = on_upgrade.await else {
+                return;
+            };
+
+            let stream = WebSocketStream::from_raw_socket(
+                TokioIo::new(upgraded),
+                Role::Server,
+                Some(config),
+            )
+            .await;
+
+            callback(WebSocket { inner: stream }).await;
+        });
+
+        let mut res = StatusCode::SWITCHING_PROTOCOLS.into_response();
+        let headers = res.headers_mut();
+        headers.insert(CONNECTION, HeaderValue::from_static("upgrade"));
+        headers.insert(UPGRADE, HeaderValue::from_static("websocket"));
+        headers.insert(SEC_WEBSOCKET_ACCEPT, accept_key(key.as_bytes()));
+        if let Some(protocol) = protocol {
+            headers.insert(SEC_WEBSOCKET_PROTOCOL, protocol);
+        }
+
+        res
+    }
+}
+
+/// Derives `Sec-WebSocket-Accept` from the client's key as per RFC 6455.
+fn accept_key(key: &[u8]) -> HeaderValue {
+    const GUID: &[u8] = b"258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+    let mut sha1 = Sha1::new();
+    sha1.update(key);
+    sha1.update(GUID);
+
+    HeaderValue::from_str(&STANDARD.encode(sha1.finalize()))
+        .expect("base64 is a valid header value")
+}
+
+/// An upgraded WebSocket connection. Besides [`recv`](WebSocket::recv) and
+/// [`send`](WebSocket::send) it implements `Stream` and `Sink`, so it can be
+/// split into independent reading and writing halves.
+pub struct WebSocket {
+    inner: WebSocketStream<TokioIo<Upgraded>>,
+}
+
+impl WebSocket {
+    /// Receives the next message, or `None` once the connection is closed.
+    /// Pings are answered automatically but still returned.
+    pub async fn recv(&mut self) -> Option<Result<Message, WebSocketError>> {
+        self.next().await
+    }
+
+    pub async fn send(&mut self, msg: Message) -> Result<(), WebSocketError> {
+        SinkExt::send(self, msg).await
+    }
+
+    /// Sends a close frame and waits for the connection to shut down.
+    pub async fn close(mut self, frame: Option<CloseFrame>) -> Result<(), WebSocketError> {
+        self.inner
+            .close(frame.map(CloseFrame::into_tungstenite))
+            .await
+            .map_err(WebSocketError)
+    }
+}
+
+impl Stream for WebSocket {
+    type Item = Result<Message, WebSocketError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            let msg = match self.inner.poll_next_unpin(cx) {
+                Poll::Ready(Some(Ok(msg))) => msg,
+                Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(WebSocketError(err)))),
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            };
+
+            // Raw frames are never produced while reading.
+            if let Some(msg) = Message::from_tungstenite(msg) {
+                return Poll::Ready(Some(Ok(msg)));
+            }
+        }
+    }
+}
+
+impl Sink<Message> for WebSocket {
+    type Error = WebSocketError;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready_unpin(cx).map_err(WebSocketError)
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, item: Message) -> Result<(), Self::Error> {
+        self.inner
+            .start_send_unpin(item.into_tungstenite())
+            .map_err(WebSocketError)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_flush_unpin(cx).map_err(WebSocketError)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_close_unpin(cx).map_err(WebSocketError)
+    }
+}
+
+/// A WebSocket message.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Message {
+    Text(String),
+    Binary(Bytes),
+    /// Must carry at most 125 bytes.
+    Ping(Bytes),
+    /// Must carry at most 125 bytes.
+    Pong(Bytes),
+    Close(Option<CloseFrame>),
+}
+
+impl Message {
+    fn into_tungstenite(self) -> protocol::Message {
+        match self {
+            Self::Text(text) => protocol::Message::Text(text.into()),
+            Self::Binary(bytes) => protocol::Message::Binary(bytes),
+            Self::Ping(bytes) => protocol::Message::Ping(bytes),
+            Self::Pong(bytes) => protocol::Message::Pong(bytes),
+            Self::Close(frame) => protocol::Message::Close(frame.map(CloseFrame::into_tungstenite)),
+        }
+    }
+
+    fn from_tungstenite(msg: protocol::Message) -> Option<Self> {
+        match msg {
+            protocol::Message::Text(text) => Some(Self::Text(text.as_str().to_string())),
+            protocol::Message::Binary(bytes) => Some(Self::Binary(bytes)),
+            protocol::Message::Ping(bytes) => Some(Self::Ping(bytes)),
+            protocol::Message::Pong(bytes) => Some(Self::Pong(bytes)),
+            protocol::Message::Close(frame) => Some(Self::Close(frame.map(|frame| CloseFrame {
+                code: frame.code.into(),
+                reason: frame.reason.as_str().to_string(),
+            }))),
+            protocol::Message::Frame(_) => None,
+        }
+    }
+}
+
+impl From<String> for Message {
+    fn from(text: String) -> Self {
+        Self::Text(text)
+    }
+}
+
+impl From<&str> for Message {
+    fn from(text: &str) -> Self {
+        Self::Text(text.to_string())
+    }
+}
+
+impl From<Bytes> for Message {
+    fn from(bytes: Bytes) -> Self {
+        Self::Binary(bytes)
+    }
+}
+
+impl From<Vec<u8>> for Message {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self::Binary(bytes.into())
+    }
+}
+
+/// The status code and reason sent with a close message.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CloseFrame {
+    pub code: u16,
+    pub reason: String,
+}
+
+impl CloseFrame {
+    fn into_tungstenite(self) -> protocol::CloseFrame {
+        protocol::CloseFrame {
+            code: CloseCode::from(self.code),
+            reason: self.reason.into(),
+        }
+    }
+}
+
+/// An error raised by the underlying WebSocket connection.
+#[derive(Debug)]
+pub struct WebSocketError(tokio_tungstenite::tungstenite::Error);
+
+impl fmt::Display for WebSocketError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "WebSocket error: {}", self.0)
+    }
+}
+
+impl std::error::Error for WebSocketError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.0)
+    }
+}
@@ -0,0 +1,119 @@
+//! Serves routers on a local port and sends them HTTP/1.1 requests.
+
+#![allow(dead_code)]
+
+use std::net::SocketAddr;
+
+use http_body_util::{BodyExt, Empty, Full};
+use hyper::body::{Bytes, Incoming};
+use hyper::{HeaderMap, Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use mini_axum::{Router, Service};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{TcpListener, TcpStream};
+
+pub async fn serve<S>(router: Router<S>) -> SocketAddr
+where
+    S: Clone + Send + Sync + 'static,
+{
+    let tcp = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = tcp.local_addr().unwrap();
+
+    let svc = Service::new(tcp, router);
+    tokio::spawn(async move {
+        let _ = svc.await;
+    });
+
+    addr
+}
+
+pub struct TestResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+
+impl TestResponse {
+    pub fn text(&self) -> &str {
+        std::str::from_utf8(&self.body).unwrap()
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).map(|value| value.to_str().unwrap())
+    }
+}
+
+pub async fn send<B>(addr: SocketAddr, req: Request<B>) -> TestResponse
+where
+    B: hyper::body::Body + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    let stream = TcpStream::connect(addr).await.unwrap();
+    send_over(stream, req).await
+}
+
+/// Sends `req` over an already open connection.
+pub async fn send_over<I, B>(io: I, req: Request<B>) -> TestResponse
+where
+    I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    B: hyper::body::Body + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    let (parts, body) = open_over(io, req).await.into_parts();
+
+    TestResponse {
+        status: parts.status,
+        headers: parts.headers,
+        body: body.collect().await.unwrap().to_bytes(),
+    }
+}
+
+/// Sends a `GET` request for `path`, returning the response before its body
+/// is read.
+pub async fn open(addr: SocketAddr, path: &str) -> Response<Incoming> {
+    let req = Request::get(path)
+        .header("host", addr.to_string())
+        .body(Empty::<Bytes>::new())
+        .unwrap();
+
+    open_over(TcpStream::connect(addr).await.unwrap(), req).await
+}
+
+async fn open_over<I, B>(io: I, req: Request<B>) -> Response<Incoming>
+where
+    I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    B: hyper::body::Body + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    let (mut sender, conn) = hyper::client::conn::http1::handshake(TokioIo::new(io))
+        .await
+        .unwrap();
+    tokio::spawn(conn);
+
+    sender.send_request(req).await.unwrap()
+}
+
+pub async fn request(
+    addr: SocketAddr,
+    method: Method,
+    path: &str,
+    headers: &[(&str, &str)],
+    body: impl Into<Bytes>,
+) -> TestResponse {
+    let mut req = Request::builder()
+        .method(method)
+        .uri(path)
+        .header("host", addr.to_string());
+    for (name, value) in headers {
+        req = req.header(*name, *value);
+    }
+
+    send(addr, req.body(Full::new(body.into())).unwrap()).await
+}
+
+pub async fn fetch(addr: SocketAddr, path: &str) -> TestResponse {
+    request(addr, Method::GET, path, &[], Bytes::new()).await
+}
@@ -0,0 +1,375 @@
+mod common;
+
+use std::convert::Infallible;
+
+use http_body_util::StreamBody;
+use hyper::body::{Bytes, Frame};
+use hyper::{Method, Request, StatusCode};
+use mini_axum::{
+    Router,
+    extractor::{DefaultBodyLimit, Query, RawQuery},
+    method_router::{get, post},
+    multipart::{Multipart, MultipartError, MultipartLimits},
+    response::{Form, Json},
+};
+use serde::{Deserialize, Serialize};
+
+use common::{fetch, request, send, serve};
+
+#[derive(Deserialize, Serialize)]
+struct User {
+    name: String,
+    age: u8,
+}
+
+const JSON: (&str, &str) = ("content-type", "application/json");
+
+async fn json_app() -> std::net::SocketAddr {
+    serve(Router::stateless().route("/", post(async |Json(user): Json<User>| Json(user)))).await
+}
+
+#[tokio::test]
+async fn extracts_json_bodies() {
+    let addr = json_app().await;
+
+    let body = r#"{"name":"ferris","age":7}"#;
+    let res = request(addr, Method::POST, "/", &[JSON], body).await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.header("content-type"), Some("application/json"));
+    assert_eq!(res.text(), body);
+
+    let res = request(
+        addr,
+        Method::POST,
+        "/",
+        &[("content-type", "application/vnd.api+json; charset=utf-8")],
+        body,
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn rejects_json_without_a_json_content_type() {
+    let addr = json_app().await;
+
+    let res = request(addr, Method::POST, "/", &[], r#"{"name":"ferris","age":7}"#).await;
+    assert_eq!(res.status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    assert_eq!(
+        res.text(),
+        "Expected request with `Content-Type: application/json`"
+    );
+}
+
+#[tokio::test]
+async fn distinguishes_json_syntax_and_data_errors() {
+    let addr = json_app().await;
+
+    let res = request(addr, Method::POST, "/", &[JSON], r#"{"name":"#).await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST);
+    assert!(
+        res.text()
+            .starts_with("Failed to parse the request body as JSON")
+    );
+
+    let res = request(
+        addr,
+        Method::POST,
+        "/",
+        &[JSON],
+        r#"{"name":"ferris","age":700}"#,
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(
+        res.text()
+            .starts_with("Failed to deserialize the JSON body into the target type")
+    );
+}
+
+#[derive(Deserialize)]
+struct Pagination {
+    page: u32,
+    per_page: Option<u32>,
+}
+
+#[tokio::test]
+async fn extracts_query_strings() {
+    let addr = serve(Router::stateless().route(
+        "/",
+        get(async |Query(p): Query<Pagination>| Json((p.page, p.per_page))),
+    ))
+    .await;
+
+    assert_eq!(fetch(addr, "/?page=2&per_page=50").await.text(), "[2,50]");
+    assert_eq!(fetch(addr, "/?page=2").await.text(), "[2,null]");
+
+    let res = fetch(addr, "/?page=two").await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST);
+    assert!(
+        res.text()
+            .starts_with("Failed to deserialize query string: `page`")
+    );
+
+    let res = fetch(addr, "/").await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn collects_repeated_query_keys_and_raw_queries() {
+    #[derive(Deserialize)]
+    struct Tags {
+        tag: Vec<String>,
+    }
+
+    let addr = serve(
+        Router::stateless()
+            .route("/tags", get(async |Query(q): Query<Tags>| Json(q.tag)))
+            .route("/raw", get(async |RawQuery(query): RawQuery| Json(query))),
+    )
+    .await;
+
+    assert_eq!(
+        fetch(addr, "/tags?tag=a&tag=b%20c").await.text(),
+        r#"["a","b c"]"#
+    );
+    assert_eq!(fetch(addr, "/raw?a=1&b").await.text(), r#""a=1&b""#);
+    assert_eq!(fetch(addr, "/raw").await.text(), "null");
+}
+
+#[derive(Deserialize, Serialize)]
+struct Login {
+    username: String,
+    remember: bool,
+}
+
+const FORM: (&str, &str) = ("content-type", "application/x-www-form-urlencoded");
+
+async fn form_app() -> std::net::SocketAddr {
+    serve(
+        Router::stateless().route(
+            "/",
+            get(async |Form(login): Form<Login>| Form(login))
+                .post(async |Form(login): Form<Login>| Form(login)),
+        ),
+    )
+    .await
+}
+
+#[tokio::test]
+async fn extracts_forms_from_bodies_and_query_strings() {
+    let addr = form_app().await;
+
+    let res = request(
+        addr,
+        Method::POST,
+        "/",
+        &[FORM],
+        "username=fer+ris&remember=true",
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(
+        res.header("content-type"),
+        Some("application/x-www-form-urlencoded")
+    );
+    assert_eq!(res.text(), "username=fer+ris&remember=true");
+
+    let res = fetch(addr, "/?username=ferris&remember=false").await;
+    assert_eq!(res.text(), "username=ferris&remember=false");
+}
+
+#[tokio::test]
+async fn rejects_invalid_forms() {
+    let addr = form_app().await;
+
+    let res = request(
+        addr,
+        Method::POST,
+        "/",
+        &[JSON],
+        "username=ferris&remember=true",
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+    let res = request(
+        addr,
+        Method::POST,
+        "/",
+        &[FORM],
+        "username=ferris&remember=maybe",
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(
+        res.text()
+            .starts_with("Failed to deserialize form: remember")
+    );
+
+    let res = fetch(addr, "/?username=ferris").await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST);
+}
+
+const MULTIPART: (&str, &str) = ("content-type", "multipart/form-data; boundary=X-BOUNDARY");
+
+fn multipart_body(fields: &[(&str, Option<&str>, &str)]) -> String {
+    let mut body = String::new();
+    for (name, file_name, value) in fields {
+        body.push_str("--X-BOUNDARY\r\n");
+        match file_name {
+            Some(file_name) => body.push_str(&format!(
+                "Content-Disposition: form-data; name=\"{name}\"; filename=\"{file_name}\"\r\n\
+                 Content-Type: text/plain\r\n"
+            )),
+            None => body.push_str(&format!(
+                "Content-Disposition: form-data; name=\"{name}\"\r\n"
+            )),
+        }
+        body.push_str(&format!("\r\n{value}\r\n"));
+    }
+    body.push_str("--X-BOUNDARY--\r\n");
+    body
+}
+
+type Field = (String, Option<String>, String);
+
+async fn describe_fields(mut multipart: Multipart) -> Result<Json<Vec<Field>>, MultipartError> {
+    let mut fields = Vec::new();
+    while let Some(field) = multipart.next_field().await? {
+        let name = field.name().unwrap_or_default().to_string();
+        let file_name = field.file_name().map(str::to_string);
+        let text = field.text().await?;
+        fields.push((name, file_name, text));
+    }
+
+    Ok(Json(fields))
+}
+
+#[tokio::test]
+async fn streams_multipart_fields() {
+    let addr = serve(Router::stateless().route("/", post(describe_fields))).await;
+
+    let body = multipart_body(&[
+        ("title", None, "Holiday"),
+        ("photo", Some("beach.txt"), "sand and sea"),
+    ]);
+    let res = request(addr, Method::POST, "/", &[MULTIPART], body).await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(
+        res.text(),
+        r#"[["title",null,"Holiday"],["photo","beach.txt","sand and sea"]]"#
+    );
+}
+
+#[tokio::test]
+async fn rejects_requests_without_a_multipart_boundary() {
+    let addr = serve(Router::stateless().route("/", post(describe_fields))).await;
+
+    let res = request(addr, Method::POST, "/", &[FORM], "a=b").await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        res.text(),
+        "Invalid `boundary` for `multipart/form-data` request"
+    );
+}
+
+#[tokio::test]
+async fn enforces_multipart_field_limits() {
+    let addr = serve(
+        Router::stateless()
+            .route("/", post(describe_fields))
+            .layer(MultipartLimits::new().per_field(8)),
+    )
+    .await;
+
+    let body = multipart_body(&[("short", None, "12345678")]);
+    let res = request(addr, Method::POST, "/", &[MULTIPART], body).await;
+    assert_eq!(res.status, StatusCode::OK);
+
+    let body = multipart_body(&[("long", None, "123456789")]);
+    let res = request(addr, Method::POST, "/", &[MULTIPART], body).await;
+    assert_eq!(res.status, StatusCode::PAYLOAD_TOO_LARGE);
+}
+
+fn json_of_len(len: usize) -> String {
+    let padding = len - r#"{"name":"","age":7}"#.len();
+    format!(r#"{{"name":"{}","age":7}}"#, "x".repeat(padding))
+}
+
+#[tokio::test]
+async fn limits_bodies_to_two_mebibytes_by_default() {
+    let addr = json_app().await;
+
+    let res = request(
+        addr,
+        Method::POST,
+        "/",
+        &[JSON],
+        json_of_len(2 * 1024 * 1024),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK);
+
+    let res = request(
+        addr,
+        Method::POST,
+        "/",
+        &[JSON],
+        json_of_len(2 * 1024 * 1024 + 1),
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::PAYLOAD_TOO_LARGE);
+    assert_eq!(
+        res.text(),
+        "Request body is larger than the limit of 2097152 bytes"
+    );
+}
+
+#[tokio::test]
+async fn configures_body_limits_per_route() {
+    let echo = async |Json(user): Json<User>| Json(user);
+    let addr = serve(
+        Router::stateless()
+            .route("/small", post(echo).layer(DefaultBodyLimit::max(32)))
+            .route("/unlimited", post(echo).layer(DefaultBodyLimit::disable())),
+    )
+    .await;
+
+    let res = request(addr, Method::POST, "/small", &[JSON], json_of_len(32)).await;
+    assert_eq!(res.status, StatusCode::OK);
+    let res = request(addr, Method::POST, "/small", &[JSON], json_of_len(33)).await;
+    assert_eq!(res.status, StatusCode::PAYLOAD_TOO_LARGE);
+
+    let body = json_of_len(3 * 1024 * 1024);
+    let res = request(addr, Method::POST, "/unlimited", &[JSON], body).await;
+    assert_eq!(res.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn limits_bodies_without_a_content_length() {
+    let addr = serve(
+        Router::stateless()
+            .route("/", post(async |Json(user): Json<User>| Json(user)))
+            .layer(DefaultBodyLimit::max(32)),
+    )
+    .await;
+
+    let chunked = |body: String| {
+        let chunks = body
+            .into_bytes()
+            .chunks(8)
+            .map(|chunk| Ok::<_, Infallible>(Frame::data(Bytes::copy_from_slice(chunk))))
+            .collect::<Vec<_>>();
+        Request::post("/")
+            .header("host", addr.to_string())
+            .header(JSON.0, JSON.1)
+            .body(StreamBody::new(futures::stream::iter(chunks)))
+            .unwrap()
+    };
+
+    let res = send(addr, chunked(json_of_len(32))).await;
+    assert_eq!(res.status, StatusCode::OK);
+    let res = send(addr, chunked(json_of_len(64))).await;
+    assert_eq!(res.status, StatusCode::PAYLOAD_TOO_LARGE);
+}
@@ -0,0 +1,76 @@
+use std::net::SocketAddr;
+
+use http_body_util::{BodyExt, Empty};
+use hyper::body::{Bytes, Incoming};
+use hyper::{Request, Response, StatusCode, Version};
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use mini_axum::{Router, Service, method_router::get};
+use tokio::net::{TcpListener, TcpStream};
+
+async fn serve(configure: fn(Service<()>) -> Service<()>) -> SocketAddr {
+    let tcp = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = tcp.local_addr().unwrap();
+
+    let rtr = Router::stateless().route("/", get(async || "Hello world!"));
+    let svc = configure(Service::new(tcp, rtr));
+    tokio::spawn(async move {
+        let _ = svc.await;
+    });
+
+    addr
+}
+
+fn get_root(addr: SocketAddr) -> Request<Empty<Bytes>> {
+    Request::get(format!("http://{addr}/"))
+        .body(Empty::new())
+        .unwrap()
+}
+
+async fn send_http1(addr: SocketAddr) -> Result<Response<Incoming>, hyper::Error> {
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let (mut sender, conn) = hyper::client::conn::http1::handshake(TokioIo::new(stream)).await?;
+    tokio::spawn(conn);
+
+    sender.send_request(get_root(addr)).await
+}
+
+/// Sends a request over cleartext HTTP/2 with prior knowledge (h2c).
+async fn send_http2(addr: SocketAddr) -> Result<Response<Incoming>, hyper::Error> {
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let (mut sender, conn) =
+        hyper::client::conn::http2::handshake(TokioExecutor::new(), TokioIo::new(stream)).await?;
+    tokio::spawn(conn);
+
+    sender.send_request(get_root(addr)).await
+}
+
+#[tokio::test]
+async fn serves_h2c_with_prior_knowledge() {
+    let addr = serve(|svc| svc).await;
+
+    let res = send_http2(addr).await.unwrap();
+    assert_eq!(res.version(), Version::HTTP_2);
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(body, "Hello world!");
+
+    let res = send_http1(addr).await.unwrap();
+    assert_eq!(res.version(), Version::HTTP_11);
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn http1_only_rejects_http2() {
+    let addr = serve(Service::http1_only).await;
+
+    assert!(send_http2(addr).await.is_err());
+    assert_eq!(send_http1(addr).await.unwrap().status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn http2_only_rejects_http1() {
+    let addr = serve(Service::http2_only).await;
+
+    assert!(send_http1(addr).await.is_err());
+    assert_eq!(send_http2(addr).await.unwrap().status(), StatusCode::OK);
+}
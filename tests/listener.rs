@@ -0,0 +1,149 @@
+mod common;
+
+use std::io;
+use std::net::SocketAddr;
+
+use http_body_util::Empty;
+use hyper::body::Bytes;
+use hyper::{Request, StatusCode};
+use mini_axum::{
+    Router, Service,
+    extractor::ConnectInfo,
+    listener::{Connected, IncomingStream, Listener},
+    method_router::get,
+};
+use tokio::net::{TcpListener, TcpStream};
+
+use common::{fetch, send_over};
+
+fn get_root() -> Request<Empty<Bytes>> {
+    Request::get("/")
+        .header("host", "localhost")
+        .body(Empty::new())
+        .unwrap()
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn serves_unix_domain_sockets() {
+    use tokio::net::{UnixListener, UnixStream};
+
+    let path = std::env::temp_dir().join(format!("mini-axum-{}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let listener = UnixListener::bind(&path).unwrap();
+
+    let rtr = Router::stateless().route("/", get(async || "Hello from a socket file!"));
+    tokio::spawn(async move {
+        let _ = Service::new(listener, rtr).await;
+    });
+
+    let res = send_over(UnixStream::connect(&path).await.unwrap(), get_root()).await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.text(), "Hello from a socket file!");
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+/// A TCP listener whose first accept fails, like one that ran out of file
+/// descriptors.
+struct FlakyListener {
+    tcp: TcpListener,
+    failed: bool,
+}
+
+impl Listener for FlakyListener {
+    type Io = TcpStream;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> io::Result<(Self::Io, Self::Addr)> {
+        if !self.failed {
+            self.failed = true;
+            return Err(io::Error::other("Too many open files"));
+        }
+
+        self.tcp.accept().await
+    }
+
+    fn local_addr(&self) -> io::Result<Self::Addr> {
+        self.tcp.local_addr()
+    }
+}
+
+#[tokio::test]
+async fn keeps_accepting_after_an_accept_error() {
+    let tcp = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = tcp.local_addr().unwrap();
+    let listener = FlakyListener { tcp, failed: false };
+
+    let rtr = Router::stateless().route("/", get(async || "Still here!"));
+    tokio::spawn(async move {
+        let _ = Service::new(listener, rtr).await;
+    });
+
+    let res = fetch(addr, "/").await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.text(), "Still here!");
+}
+
+async fn serve_connect_info(
+    router: Router,
+    configure: fn(Service<()>) -> Service<()>,
+) -> SocketAddr {
+    let tcp = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = tcp.local_addr().unwrap();
+
+    let svc = configure(Service::new(tcp, router));
+    tokio::spawn(async move {
+        let _ = svc.await;
+    });
+
+    addr
+}
+
+#[tokio::test]
+async fn extracts_the_peer_address_of_tcp_connections() {
+    let rtr = Router::stateless().route(
+        "/",
+        get(async |ConnectInfo(peer): ConnectInfo<SocketAddr>| peer.to_string()),
+    );
+    let addr = serve_connect_info(rtr, |svc| svc).await;
+
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let peer = stream.local_addr().unwrap();
+    let res = send_over(stream, get_root()).await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.text(), peer.to_string());
+}
+
+#[derive(Clone)]
+struct PeerPort(u16);
+
+impl Connected<IncomingStream<'_, TcpListener>> for PeerPort {
+    fn connect_info(stream: IncomingStream<'_, TcpListener>) -> Self {
+        PeerPort(stream.remote_addr().port())
+    }
+}
+
+#[tokio::test]
+async fn extracts_custom_connect_info() {
+    let rtr = Router::stateless()
+        .route(
+            "/",
+            get(async |ConnectInfo(PeerPort(port)): ConnectInfo<PeerPort>| port.to_string()),
+        )
+        .route(
+            "/addr",
+            get(async |ConnectInfo(peer): ConnectInfo<SocketAddr>| peer.to_string()),
+        );
+    let addr = serve_connect_info(rtr, Service::connect_info::<PeerPort>).await;
+
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let port = stream.local_addr().unwrap().port();
+    let res = send_over(stream, get_root()).await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.text(), port.to_string());
+
+    // Only the selected type is available.
+    let res = fetch(addr, "/addr").await;
+    assert_eq!(res.status, StatusCode::INTERNAL_SERVER_ERROR);
+}
@@ -0,0 +1,443 @@
+mod common;
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use hyper::body::Incoming;
+use hyper::header::{AUTHORIZATION, HeaderValue};
+use hyper::{HeaderMap, Method, Request, Response, StatusCode};
+use mini_axum::{
+    Router,
+    body::Body,
+    endpoint::Handler,
+    extractor::{Query, State},
+    method_router::{get, post},
+    middleware::{
+        LogFormat, LogLayer, Next, from_fn, from_fn_with_state, map_request,
+        map_request_with_state, map_response, map_response_with_state,
+    },
+    response::{IntoMiniResponse, MiniResponse},
+};
+use serde_json::Value;
+use tower::Layer;
+
+use common::{fetch, request, serve};
+
+async fn count_hits(
+    State(hits): State<Arc<AtomicUsize>>,
+    req: Request<Incoming>,
+    next: Next,
+) -> MiniResponse {
+    hits.fetch_add(1, Ordering::SeqCst);
+    next.run(req).await.into_response()
+}
+
+#[tokio::test]
+async fn layers_wrap_method_not_allowed_and_options_responses() {
+    let hits = Arc::new(AtomicUsize::new(0));
+    let addr = serve(
+        Router::stateless()
+            .route("/a", get(async || "a"))
+            .layer(from_fn_with_state(hits.clone(), count_hits)),
+    )
+    .await;
+
+    let res = request(addr, Method::POST, "/a", &[], "").await;
+    assert_eq!(res.status, StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(hits.load(Ordering::SeqCst), 1);
+
+    let res = request(addr, Method::OPTIONS, "/a", &[], "").await;
+    assert_eq!(res.status, StatusCode::NO_CONTENT);
+    assert_eq!(hits.load(Ordering::SeqCst), 2);
+
+    let res = request(addr, Method::HEAD, "/a", &[], "").await;
+    assert_eq!(res.header("content-length"), Some("1"));
+    assert_eq!(hits.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn route_layers_skip_method_not_allowed_and_options_responses() {
+    let hits = Arc::new(AtomicUsize::new(0));
+    let addr = serve(
+        Router::stateless()
+            .route("/a", get(async || "a"))
+            .route_layer(from_fn_with_state(hits.clone(), count_hits)),
+    )
+    .await;
+
+    let res = request(addr, Method::POST, "/a", &[], "").await;
+    assert_eq!(res.status, StatusCode::METHOD_NOT_ALLOWED);
+    let res = request(addr, Method::OPTIONS, "/a", &[], "").await;
+    assert_eq!(res.status, StatusCode::NO_CONTENT);
+    assert_eq!(hits.load(Ordering::SeqCst), 0);
+
+    assert_eq!(fetch(addr, "/a").await.text(), "a");
+    request(addr, Method::HEAD, "/a", &[], "").await;
+    assert_eq!(hits.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn merged_routes_keep_the_layers_of_their_router() {
+    let outer = Arc::new(AtomicUsize::new(0));
+    let inner = Arc::new(AtomicUsize::new(0));
+    let other = Router::stateless()
+        .route("/a", post(async || "post"))
+        .layer(from_fn_with_state(inner.clone(), count_hits));
+    let addr = serve(
+        Router::stateless()
+            .route("/a", get(async || "get"))
+            .merge(other)
+            .layer(from_fn_with_state(outer.clone(), count_hits)),
+    )
+    .await;
+
+    assert_eq!(fetch(addr, "/a").await.text(), "get");
+    assert_eq!(
+        (outer.load(Ordering::SeqCst), inner.load(Ordering::SeqCst)),
+        (1, 0)
+    );
+
+    let res = request(addr, Method::POST, "/a", &[], "").await;
+    assert_eq!(res.text(), "post");
+    assert_eq!(
+        (outer.load(Ordering::SeqCst), inner.load(Ordering::SeqCst)),
+        (2, 1)
+    );
+
+    let res = request(addr, Method::DELETE, "/a", &[], "").await;
+    assert_eq!(res.header("allow"), Some("GET,POST,HEAD,OPTIONS"));
+    assert_eq!(
+        (outer.load(Ordering::SeqCst), inner.load(Ordering::SeqCst)),
+        (3, 1)
+    );
+}
+
+/// Counts how many services it wraps.
+#[derive(Clone)]
+struct CountLayering(Arc<AtomicUsize>);
+
+impl<S> Layer<S> for CountLayering {
+    type Service = S;
+
+    fn layer(&self, inner: S) -> S {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        inner
+    }
+}
+
+#[tokio::test]
+async fn wraps_layered_handlers_once() {
+    let layered = Arc::new(AtomicUsize::new(0));
+    let handler = (async || "a").layer(CountLayering(layered.clone()));
+    let addr = serve(Router::stateless().route("/a", get(handler))).await;
+
+    for _ in 0..3 {
+        assert_eq!(fetch(addr, "/a").await.text(), "a");
+    }
+    assert_eq!(layered.load(Ordering::SeqCst), 1);
+}
+
+async fn require_auth(headers: HeaderMap, req: Request<Incoming>, next: Next) -> MiniResponse {
+    match headers.get(AUTHORIZATION) {
+        Some(_) => next.run(req).await.into_response(),
+        None => StatusCode::UNAUTHORIZED.into_response(),
+    }
+}
+
+async fn append_trace(name: &'static str, mut res: Response<Body>) -> Response<Body> {
+    let trace = match res.headers().get("x-trace") {
+        Some(trace) => format!("{},{name}", trace.to_str().unwrap()),
+        None => name.to_string(),
+    };
+    res.headers_mut()
+        .insert("x-trace", HeaderValue::from_str(&trace).unwrap());
+    res
+}
+
+async fn append_trace_with_state(
+    State(name): State<&'static str>,
+    res: Response<Body>,
+) -> Response<Body> {
+    append_trace(name, res).await
+}
+
+#[tokio::test]
+async fn from_fn_runs_around_the_handler() {
+    let addr = serve(
+        Router::stateless()
+            .route("/a", get(async || "a"))
+            .layer(from_fn(require_auth)),
+    )
+    .await;
+
+    let res = fetch(addr, "/a").await;
+    assert_eq!(res.status, StatusCode::UNAUTHORIZED);
+
+    let res = request(addr, Method::GET, "/a", &[("authorization", "token")], "").await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.text(), "a");
+}
+
+#[tokio::test]
+async fn route_layers_leave_unmatched_paths_alone() {
+    let addr = serve(
+        Router::stateless()
+            .route("/a", get(async || "a"))
+            .route_layer(from_fn(require_auth)),
+    )
+    .await;
+
+    assert_eq!(fetch(addr, "/a").await.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(fetch(addr, "/nope").await.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn route_layers_leave_unmatched_paths_of_nested_routers_alone() {
+    let api = Router::stateless()
+        .route("/users", get(async || "users"))
+        .nest_service(
+            "/files",
+            tower::service_fn(async |_req| Ok::<_, Infallible>(Response::new(Body::from("files")))),
+        );
+    let addr = serve(
+        Router::stateless()
+            .nest("/api", api)
+            .route_layer(from_fn(require_auth)),
+    )
+    .await;
+
+    assert_eq!(
+        fetch(addr, "/api/users").await.status,
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        fetch(addr, "/api/files/a").await.status,
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(fetch(addr, "/api/nope").await.status, StatusCode::NOT_FOUND);
+    assert_eq!(fetch(addr, "/nope").await.status, StatusCode::NOT_FOUND);
+
+    let res = request(addr, Method::POST, "/api/users", &[], "").await;
+    assert_eq!(res.status, StatusCode::METHOD_NOT_ALLOWED);
+
+    let res = request(
+        addr,
+        Method::GET,
+        "/api/users",
+        &[("authorization", "token")],
+        "",
+    )
+    .await;
+    assert_eq!(res.text(), "users");
+}
+
+#[tokio::test]
+async fn map_request_transforms_or_rejects_the_request() {
+    async fn require_name(
+        Query(query): Query<HashMap<String, String>>,
+        mut req: Request<Incoming>,
+    ) -> Result<Request<Incoming>, (StatusCode, &'static str)> {
+        let name = query
+            .get("name")
+            .ok_or((StatusCode::BAD_REQUEST, "no name"))?;
+        req.headers_mut()
+            .insert("x-name", HeaderValue::from_str(name).unwrap());
+        Ok(req)
+    }
+
+    let calls = Arc::new(AtomicUsize::new(0));
+    let addr = serve(
+        Router::with_state(calls.clone())
+            .route(
+                "/",
+                get(
+                    async |State(calls): State<Arc<AtomicUsize>>, headers: HeaderMap| {
+                        calls.fetch_add(1, Ordering::SeqCst);
+                        format!("hello {}", headers["x-name"].to_str().unwrap())
+                    },
+                ),
+            )
+            .layer(map_request(require_name)),
+    )
+    .await;
+
+    assert_eq!(fetch(addr, "/?name=ferris").await.text(), "hello ferris");
+
+    let res = fetch(addr, "/").await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST);
+    assert_eq!(res.text(), "no name");
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn map_request_with_state_extracts_the_state() {
+    async fn add_tenant(
+        State(tenant): State<&'static str>,
+        mut req: Request<Incoming>,
+    ) -> Request<Incoming> {
+        req.headers_mut()
+            .insert("x-tenant", HeaderValue::from_static(tenant));
+        req
+    }
+
+    let addr = serve(
+        Router::stateless()
+            .route(
+                "/",
+                get(async |headers: HeaderMap| headers["x-tenant"].to_str().unwrap().to_string()),
+            )
+            .layer(map_request_with_state("acme", add_tenant)),
+    )
+    .await;
+
+    assert_eq!(fetch(addr, "/").await.text(), "acme");
+}
+
+#[tokio::test]
+async fn map_response_transforms_the_response_in_layer_order() {
+    let addr = serve(
+        Router::stateless()
+            .route("/", get(async || "index"))
+            .layer(map_response_with_state("inner", append_trace_with_state))
+            .layer(map_response(async |res: Response<Body>| {
+                append_trace("outer", res).await
+            })),
+    )
+    .await;
+
+    let res = fetch(addr, "/").await;
+    assert_eq!(res.text(), "index");
+    assert_eq!(res.header("x-trace"), Some("inner,outer"));
+}
+
+#[tokio::test]
+async fn handler_layers_only_wrap_their_handler() {
+    let addr = serve(
+        Router::stateless()
+            .route("/public", get(async || "public"))
+            .route(
+                "/admin",
+                get((async || "admin").layer(from_fn(require_auth))),
+            ),
+    )
+    .await;
+
+    assert_eq!(fetch(addr, "/public").await.text(), "public");
+    assert_eq!(fetch(addr, "/admin").await.status, StatusCode::UNAUTHORIZED);
+
+    let res = request(
+        addr,
+        Method::GET,
+        "/admin",
+        &[("authorization", "token")],
+        "",
+    )
+    .await;
+    assert_eq!(res.text(), "admin");
+}
+
+/// Collects what a `tracing_subscriber` writes.
+#[derive(Clone, Default)]
+struct Captured(Arc<Mutex<Vec<u8>>>);
+
+impl Captured {
+    fn lines(&self) -> Vec<String> {
+        let output = self.0.lock().unwrap();
+        String::from_utf8_lossy(&output)
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+/// Captures the events of the current thread until the guard is dropped.
+/// The test runtime runs the server on the test's thread.
+fn capture_logs() -> (Captured, tracing::subscriber::DefaultGuard) {
+    let captured = Captured::default();
+    let writer = captured.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .with_ansi(false)
+        .with_writer(move || writer.clone())
+        .finish();
+
+    (captured, tracing::subscriber::set_default(subscriber))
+}
+
+impl io::Write for Captured {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn logs_method_not_allowed_and_options_responses() {
+    let (captured, _guard) = capture_logs();
+
+    let addr = serve(
+        Router::stateless()
+            .route("/a", get(async || "a"))
+            .layer(LogLayer::new()),
+    )
+    .await;
+
+    request(addr, Method::POST, "/a", &[], "").await;
+    request(addr, Method::OPTIONS, "/a", &[], "").await;
+
+    let lines = captured.lines();
+    assert_eq!(lines.len(), 2, "{lines:?}");
+    assert!(lines[0].contains("mini_axum::access"), "{}", lines[0]);
+    assert!(
+        lines[0].contains("\"POST /a HTTP/1.1\" 405"),
+        "{}",
+        lines[0]
+    );
+    assert!(
+        lines[1].contains("\"OPTIONS /a HTTP/1.1\" 204"),
+        "{}",
+        lines[1]
+    );
+}
+
+#[tokio::test]
+async fn logs_json_lines_with_the_matched_path_and_peer() {
+    let (captured, _guard) = capture_logs();
+
+    let api = Router::stateless().route("/users/{id}", get(async || "user"));
+    let addr = serve(
+        Router::stateless()
+            .nest("/api", api)
+            .layer(LogLayer::new().format(LogFormat::Json)),
+    )
+    .await;
+
+    fetch(addr, "/api/users/1?full=true").await;
+
+    let lines = captured.lines();
+    assert_eq!(lines.len(), 1, "{lines:?}");
+    let line = &lines[0];
+    assert!(line.contains(r#"matched_path="/api/users/{id}""#), "{line}");
+    assert!(line.contains("peer=127.0.0.1:"), "{line}");
+
+    // The message comes before the fields.
+    let json = &line[line.find('{').unwrap()..];
+    let entry = serde_json::Deserializer::from_str(json)
+        .into_iter::<Value>()
+        .next()
+        .unwrap()
+        .unwrap();
+    assert_eq!(entry["method"], "GET");
+    assert_eq!(entry["path"], "/api/users/1");
+    assert_eq!(entry["matched_path"], "/api/users/{id}");
+    assert_eq!(entry["status"], 200);
+    assert_eq!(entry["bytes"], 4);
+    assert!(entry["peer"].as_str().unwrap().starts_with("127.0.0.1:"));
+    assert!(entry["latency_ms"].is_f64());
+}
@@ -0,0 +1,116 @@
+mod common;
+
+use std::borrow::Cow;
+use std::convert::Infallible;
+use std::time::Duration;
+
+use futures::StreamExt;
+use http_body_util::BodyExt;
+use hyper::StatusCode;
+use mini_axum::{
+    Router,
+    body::Body,
+    method_router::get,
+    sse::{Event, KeepAlive, Sse},
+};
+
+use common::{fetch, open, serve};
+
+#[tokio::test]
+async fn sends_text_as_utf8_plain_text() {
+    let addr = serve(
+        Router::stateless()
+            .route("/str", get(async || "héllo"))
+            .route("/string", get(async || format!("{} items", 3)))
+            .route(
+                "/cow",
+                get(async || Cow::<'static, str>::Borrowed("borrowed")),
+            )
+            .route(
+                "/box",
+                get(async || (StatusCode::CREATED, Box::<str>::from("boxed"))),
+            ),
+    )
+    .await;
+
+    for (path, status, text) in [
+        ("/str", StatusCode::OK, "héllo"),
+        ("/string", StatusCode::OK, "3 items"),
+        ("/cow", StatusCode::OK, "borrowed"),
+        ("/box", StatusCode::CREATED, "boxed"),
+    ] {
+        let res = fetch(addr, path).await;
+        assert_eq!(res.status, status, "{path}");
+        assert_eq!(res.text(), text, "{path}");
+        assert_eq!(
+            res.header("content-type"),
+            Some("text/plain; charset=utf-8"),
+            "{path}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn streams_bodies_without_a_content_length() {
+    let addr = serve(Router::stateless().route(
+        "/export",
+        get(async || {
+            let rows = futures::stream::iter(0..3).then(async |i| {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                Ok::<_, Infallible>(format!("row {i}\n"))
+            });
+
+            Body::from_stream(rows)
+        }),
+    ))
+    .await;
+
+    let res = fetch(addr, "/export").await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.text(), "row 0\nrow 1\nrow 2\n");
+    assert_eq!(res.header("content-length"), None);
+    assert_eq!(res.header("transfer-encoding"), Some("chunked"));
+}
+
+#[tokio::test]
+async fn streams_server_sent_events_with_keep_alive_comments() {
+    let addr = serve(Router::stateless().route(
+        "/events",
+        get(async || {
+            let first = futures::stream::iter([Event::default().data("first")]);
+            let second = futures::stream::once(async {
+                tokio::time::sleep(Duration::from_millis(250)).await;
+                Event::default().event("done").data("second")
+            });
+            let events = first.chain(second).map(Ok::<_, Infallible>);
+
+            Sse::new(events).keep_alive(
+                KeepAlive::new()
+                    .interval(Duration::from_millis(50))
+                    .text("ping"),
+            )
+        }),
+    ))
+    .await;
+
+    let res = open(addr, "/events").await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers()["content-type"], "text/event-stream");
+    assert_eq!(res.headers()["cache-control"], "no-cache");
+
+    let mut body = res.into_body();
+    let mut chunks = Vec::new();
+    while let Some(frame) = body.frame().await {
+        let data = frame.unwrap().into_data().unwrap();
+        chunks.push(String::from_utf8(data.to_vec()).unwrap());
+    }
+
+    assert_eq!(chunks.first().unwrap(), "data: first\n\n");
+    assert_eq!(chunks.last().unwrap(), "event: done\ndata: second\n\n");
+    let keep_alives = &chunks[1..chunks.len() - 1];
+    assert!(!keep_alives.is_empty(), "{chunks:?}");
+    assert!(
+        keep_alives.iter().all(|chunk| chunk == ": ping\n\n"),
+        "{chunks:?}"
+    );
+}
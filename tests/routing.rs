@@ -0,0 +1,463 @@
+mod common;
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use http_body_util::Full;
+use hyper::body::{Bytes, Incoming};
+use hyper::{HeaderMap, Method, Request, Response, StatusCode, Uri};
+use mini_axum::{
+    Router,
+    extractor::{OriginalUri, Path, Query, State},
+    method_router::{get, post},
+    response::Json,
+};
+use serde::Deserialize;
+use tower::util::MapRequestLayer;
+
+use common::{fetch, request, serve};
+
+#[derive(Deserialize)]
+struct PostParams {
+    user: u32,
+    post: String,
+}
+
+#[tokio::test]
+async fn extracts_path_parameters() {
+    let addr = serve(
+        Router::stateless()
+            .route(
+                "/users/{id}",
+                get(async |Path(id): Path<u32>| format!("user {id}")),
+            )
+            .route(
+                "/users/{id}/posts/{post}",
+                get(async |Path((user, post)): Path<(u32, String)>| format!("{user}: {post}")),
+            )
+            .route(
+                "/named/{user}/{post}",
+                get(async |Path(params): Path<PostParams>| {
+                    format!("{}: {}", params.user, params.post)
+                }),
+            )
+            .route(
+                "/files/{*rest}",
+                get(async |Path(params): Path<HashMap<String, String>>| params["rest"].clone()),
+            ),
+    )
+    .await;
+
+    assert_eq!(fetch(addr, "/users/7").await.text(), "user 7");
+    assert_eq!(fetch(addr, "/users/7/posts/hello").await.text(), "7: hello");
+    assert_eq!(fetch(addr, "/named/7/hello").await.text(), "7: hello");
+    assert_eq!(fetch(addr, "/files/a/b/c.txt").await.text(), "a/b/c.txt");
+}
+
+#[tokio::test]
+async fn percent_decodes_path_parameters() {
+    let addr =
+        serve(Router::stateless().route("/say/{word}", get(async |Path(word): Path<String>| word)))
+            .await;
+
+    assert_eq!(
+        fetch(addr, "/say/hello%20w%C3%B6rld").await.text(),
+        "hello wörld"
+    );
+
+    let res = fetch(addr, "/say/%FF").await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST);
+    assert_eq!(res.text(), "Invalid UTF-8 in path parameter `word`");
+}
+
+#[tokio::test]
+async fn rejects_path_parameters_of_the_wrong_type() {
+    let addr = serve(Router::stateless().route(
+        "/users/{id}",
+        get(async |Path(id): Path<u32>| id.to_string()),
+    ))
+    .await;
+
+    let res = fetch(addr, "/users/ferris").await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        res.text(),
+        "Invalid URL: Cannot parse `id` with value `ferris` to a `u32`"
+    );
+}
+
+#[derive(Deserialize)]
+struct Search {
+    q: String,
+}
+
+#[derive(Deserialize)]
+struct Greeted {
+    name: String,
+}
+
+async fn greet(
+    State(greeting): State<&'static str>,
+    headers: HeaderMap,
+    Query(search): Query<Search>,
+    Json(body): Json<Greeted>,
+) -> String {
+    let from = headers["x-from"].to_str().unwrap();
+    format!("{greeting} {}, {} from {from}", body.name, search.q)
+}
+
+#[tokio::test]
+async fn calls_handlers_with_state_headers_query_and_body() {
+    let addr = serve(Router::with_state("Hello").route("/greet", post(greet))).await;
+
+    let res = request(
+        addr,
+        Method::POST,
+        "/greet?q=search",
+        &[("content-type", "application/json"), ("x-from", "tests")],
+        r#"{"name":"ferris"}"#,
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.text(), "Hello ferris, search from tests");
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn sixteen(
+    State(a): State<u32>,
+    State(b): State<u32>,
+    State(c): State<u32>,
+    State(d): State<u32>,
+    State(e): State<u32>,
+    State(f): State<u32>,
+    State(g): State<u32>,
+    State(h): State<u32>,
+    State(i): State<u32>,
+    State(j): State<u32>,
+    State(k): State<u32>,
+    State(l): State<u32>,
+    method: Method,
+    uri: Uri,
+    _headers: HeaderMap,
+    Json(last): Json<u32>,
+) -> String {
+    let sum = a + b + c + d + e + f + g + h + i + j + k + l + last;
+    format!("{method} {uri}: {sum}")
+}
+
+#[tokio::test]
+async fn calls_handlers_with_sixteen_arguments() {
+    let addr = serve(Router::with_state(1).route("/sum", post(sixteen))).await;
+
+    let res = request(
+        addr,
+        Method::POST,
+        "/sum",
+        &[("content-type", "application/json")],
+        "4",
+    )
+    .await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.text(), "POST /sum: 16");
+}
+
+#[tokio::test]
+async fn dispatches_on_the_request_method() {
+    let addr = serve(
+        Router::stateless().route(
+            "/users",
+            get(async || "list")
+                .post(async || "create")
+                .delete(async || "delete"),
+        ),
+    )
+    .await;
+
+    assert_eq!(fetch(addr, "/users").await.text(), "list");
+    let res = request(addr, Method::POST, "/users", &[], "").await;
+    assert_eq!(res.text(), "create");
+    let res = request(addr, Method::DELETE, "/users", &[], "").await;
+    assert_eq!(res.text(), "delete");
+}
+
+#[tokio::test]
+async fn answers_head_with_the_get_handler_without_a_body() {
+    let addr = serve(Router::stateless().route("/", get(async || "Hello world!"))).await;
+
+    let res = request(addr, Method::HEAD, "/", &[], "").await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.header("content-length"), Some("12"));
+    assert!(res.body.is_empty());
+}
+
+#[tokio::test]
+async fn lists_allowed_methods_for_options_and_unhandled_methods() {
+    let addr = serve(Router::stateless().route("/", get(async || "").post(async || ""))).await;
+
+    let res = request(addr, Method::OPTIONS, "/", &[], "").await;
+    assert_eq!(res.status, StatusCode::NO_CONTENT);
+    assert_eq!(res.header("allow"), Some("GET,POST,HEAD,OPTIONS"));
+
+    let res = request(addr, Method::PUT, "/", &[], "").await;
+    assert_eq!(res.status, StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(res.header("allow"), Some("GET,POST,HEAD,OPTIONS"));
+}
+
+#[tokio::test]
+async fn prefers_registered_head_and_options_handlers() {
+    let addr = serve(
+        Router::stateless().route(
+            "/",
+            get(async || "get")
+                .head(async || StatusCode::ACCEPTED)
+                .options(async || "options"),
+        ),
+    )
+    .await;
+
+    let res = request(addr, Method::HEAD, "/", &[], "").await;
+    assert_eq!(res.status, StatusCode::ACCEPTED);
+    let res = request(addr, Method::OPTIONS, "/", &[], "").await;
+    assert_eq!(res.text(), "options");
+}
+
+#[test]
+#[should_panic(expected = "Overlapping method route")]
+fn panics_on_a_method_registered_twice() {
+    let _ = get::<_, _, ()>(async || "a").get(async || "b");
+}
+
+#[test]
+#[should_panic(expected = "Overlapping method route")]
+fn panics_on_a_method_registered_twice_for_the_same_route() {
+    let _ = Router::stateless()
+        .route("/", get(async || "a"))
+        .route("/", get(async || "b").post(async || "c"));
+}
+
+#[tokio::test]
+async fn strips_the_prefix_for_nested_routers() {
+    let users = Router::stateless()
+        .route("/", get(async |uri: Uri| format!("index {uri}")))
+        .route(
+            "/{id}",
+            get(async |uri: Uri, OriginalUri(original): OriginalUri| {
+                format!("{uri} from {original}")
+            }),
+        );
+    let addr = serve(Router::stateless().nest("/users", users)).await;
+
+    assert_eq!(fetch(addr, "/users").await.text(), "index /");
+    assert_eq!(fetch(addr, "/users/").await.text(), "index /");
+    assert_eq!(
+        fetch(addr, "/users/7?full=1").await.text(),
+        "/7?full=1 from /users/7?full=1"
+    );
+    assert_eq!(
+        fetch(addr, "/users/7/posts").await.status,
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(fetch(addr, "/usersx").await.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn passes_outer_parameters_to_nested_routers() {
+    let members = Router::stateless().route(
+        "/members/{member}",
+        get(async |Path((team, member)): Path<(String, u32)>| format!("{team}: {member}")),
+    );
+    let addr = serve(Router::stateless().nest("/teams/{team}", members)).await;
+
+    assert_eq!(fetch(addr, "/teams/rust/members/3").await.text(), "rust: 3");
+}
+
+#[tokio::test]
+async fn nests_routers_with_their_own_state() {
+    let api = Router::with_state("v1").route("/", get(async |State(v): State<&'static str>| v));
+    let deeper = Router::stateless().nest("/api", api);
+    let addr = serve(Router::with_state(42).nest("/deep", deeper)).await;
+
+    assert_eq!(fetch(addr, "/deep/api").await.text(), "v1");
+}
+
+#[tokio::test]
+async fn nests_any_tower_service() {
+    let echo_path = tower::service_fn(async |req: Request<Incoming>| {
+        Ok::<_, Infallible>(Response::new(Full::new(Bytes::from(req.uri().to_string()))))
+    });
+    let failing = tower::service_fn(async |_req: Request<Incoming>| {
+        Err::<Response<Full<Bytes>>, _>(std::io::Error::other("boom"))
+    });
+    let addr = serve(
+        Router::stateless()
+            .nest_service("/static", echo_path)
+            .nest_service("/broken", failing),
+    )
+    .await;
+
+    assert_eq!(
+        fetch(addr, "/static/css/site.css").await.text(),
+        "/css/site.css"
+    );
+    let res = request(addr, Method::POST, "/static", &[], "").await;
+    assert_eq!(res.text(), "/");
+
+    let res = fetch(addr, "/broken/x").await;
+    assert_eq!(res.status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(res.text(), "boom");
+}
+
+#[test]
+#[should_panic(expected = "Nesting at the root is not supported")]
+fn panics_when_nesting_at_the_root() {
+    let _ = Router::stateless().nest("/", Router::stateless());
+}
+
+#[test]
+#[should_panic(expected = "conflicts with a router or service nested at the same path")]
+fn panics_when_a_route_conflicts_with_a_nested_router() {
+    let _ = Router::stateless()
+        .nest("/api", Router::stateless())
+        .route("/api", get(async || ""));
+}
+
+#[tokio::test]
+async fn merges_routes_of_routers_with_different_state() {
+    let users = Router::with_state("users")
+        .route("/users", get(async |State(name): State<&'static str>| name));
+    let admin = Router::with_state(7u32)
+        .route(
+            "/users",
+            post(async |State(level): State<u32>| level.to_string()),
+        )
+        .route("/admin", get(async || "admin"));
+    let addr = serve(users.merge(admin)).await;
+
+    assert_eq!(fetch(addr, "/users").await.text(), "users");
+    let res = request(addr, Method::POST, "/users", &[], "").await;
+    assert_eq!(res.text(), "7");
+    assert_eq!(fetch(addr, "/admin").await.text(), "admin");
+}
+
+#[test]
+#[should_panic(expected = "Overlapping method route: a handler for `GET /users`")]
+fn panics_when_merging_the_same_method_and_route() {
+    let _ = Router::stateless()
+        .route("/users", get(async || "a"))
+        .merge(Router::stateless().route("/users", get(async || "b")));
+}
+
+#[test]
+#[should_panic(expected = "conflicts with the existing route `/users/{id}`")]
+fn panics_when_merging_conflicting_routes() {
+    let _ = Router::stateless()
+        .route("/users/{id}", get(async || "a"))
+        .merge(Router::stateless().route("/users/{name}", get(async || "b")));
+}
+
+#[tokio::test]
+async fn uses_the_fallback_for_unmatched_paths() {
+    let addr = serve(
+        Router::stateless()
+            .route("/", get(async || "index"))
+            .fallback(async |uri: Uri| (StatusCode::NOT_FOUND, format!("no {uri}"))),
+    )
+    .await;
+
+    let res = fetch(addr, "/nope").await;
+    assert_eq!(res.status, StatusCode::NOT_FOUND);
+    assert_eq!(res.text(), "no /nope");
+
+    let res = fetch(addr, "/").await;
+    assert_eq!(res.text(), "index");
+}
+
+#[tokio::test]
+async fn nested_routers_inherit_the_fallback() {
+    let addr = serve(
+        Router::stateless()
+            .nest(
+                "/api",
+                Router::stateless().route("/users", get(async || "users")),
+            )
+            .nest(
+                "/admin",
+                Router::stateless()
+                    .route("/", get(async || "admin"))
+                    .fallback(async || "admin fallback"),
+            )
+            .fallback(async || "outer fallback"),
+    )
+    .await;
+
+    assert_eq!(fetch(addr, "/api/nope").await.text(), "outer fallback");
+    assert_eq!(fetch(addr, "/admin/nope").await.text(), "admin fallback");
+    assert_eq!(fetch(addr, "/nope").await.text(), "outer fallback");
+}
+
+#[tokio::test]
+async fn uses_the_method_not_allowed_fallback_with_the_allow_header() {
+    let addr = serve(
+        Router::stateless()
+            .route("/users", get(async || "users"))
+            .nest(
+                "/api",
+                Router::stateless().route("/items", post(async || "created")),
+            )
+            .method_not_allowed_fallback(async |method: Method| {
+                (StatusCode::METHOD_NOT_ALLOWED, format!("no {method}"))
+            }),
+    )
+    .await;
+
+    let res = request(addr, Method::DELETE, "/users", &[], "").await;
+    assert_eq!(res.status, StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(res.text(), "no DELETE");
+    assert_eq!(res.header("allow"), Some("GET,HEAD,OPTIONS"));
+
+    let res = fetch(addr, "/api/items").await;
+    assert_eq!(res.text(), "no GET");
+    assert_eq!(res.header("allow"), Some("POST,OPTIONS"));
+}
+
+#[tokio::test]
+async fn runs_outer_layers_once_for_inherited_fallbacks() {
+    let hits = Arc::new(AtomicUsize::new(0));
+    let addr = serve(
+        Router::stateless()
+            .nest(
+                "/api",
+                Router::stateless().route("/users", get(async || "users")),
+            )
+            .fallback(async || "fallback")
+            .method_not_allowed_fallback(async || "not allowed")
+            .layer(MapRequestLayer::new({
+                let hits = hits.clone();
+                move |req| {
+                    hits.fetch_add(1, Ordering::SeqCst);
+                    req
+                }
+            })),
+    )
+    .await;
+
+    assert_eq!(fetch(addr, "/nope").await.text(), "fallback");
+    assert_eq!(hits.load(Ordering::SeqCst), 1);
+
+    assert_eq!(fetch(addr, "/api/nope").await.text(), "fallback");
+    assert_eq!(hits.load(Ordering::SeqCst), 2);
+
+    let res = request(addr, Method::POST, "/api/users", &[], "").await;
+    assert_eq!(res.text(), "not allowed");
+    assert_eq!(hits.load(Ordering::SeqCst), 3);
+}
+
+#[test]
+#[should_panic(expected = "Cannot merge two routers that both have a fallback")]
+fn panics_when_merging_two_fallbacks() {
+    let _ = Router::stateless()
+        .fallback(async || "a")
+        .merge(Router::stateless().fallback(async || "b"));
+}
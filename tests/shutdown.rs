@@ -0,0 +1,121 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use mini_axum::{Router, Service, method_router::get};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+/// Serves `router` until the returned sender is used or dropped.
+async fn serve(
+    router: Router,
+    timeout: Option<Duration>,
+) -> (SocketAddr, oneshot::Sender<()>, JoinHandle<()>) {
+    let tcp = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = tcp.local_addr().unwrap();
+    let (tx, rx) = oneshot::channel::<()>();
+
+    let mut svc = Service::new(tcp, router).with_graceful_shutdown(async {
+        let _ = rx.await;
+    });
+    if let Some(timeout) = timeout {
+        svc = svc.graceful_shutdown_timeout(timeout);
+    }
+    let server = tokio::spawn(async move { svc.await.unwrap() });
+
+    (addr, tx, server)
+}
+
+async fn send_get(addr: SocketAddr, path: &str) -> TcpStream {
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(format!("GET {path} HTTP/1.1\r\nhost: x\r\n\r\n").as_bytes())
+        .await
+        .unwrap();
+    stream
+}
+
+#[tokio::test]
+async fn closes_idle_connections() {
+    let (addr, shutdown, server) = serve(Router::stateless(), None).await;
+
+    let _idle = TcpStream::connect(addr).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    shutdown.send(()).unwrap();
+
+    tokio::time::timeout(Duration::from_secs(2), server)
+        .await
+        .expect("idle connections don't hold up the shutdown")
+        .unwrap();
+}
+
+#[tokio::test]
+async fn finishes_in_flight_requests() {
+    let router = Router::stateless().route(
+        "/slow",
+        get(async || {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            "done"
+        }),
+    );
+    let (addr, shutdown, server) = serve(router, None).await;
+
+    let mut stream = send_get(addr, "/slow").await;
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    shutdown.send(()).unwrap();
+
+    let mut res = String::new();
+    stream.read_to_string(&mut res).await.unwrap();
+    assert!(res.starts_with("HTTP/1.1 200 OK"));
+    assert!(res.ends_with("done"));
+
+    tokio::time::timeout(Duration::from_secs(2), server)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(TcpStream::connect(addr).await.is_err());
+}
+
+#[tokio::test]
+async fn aborts_connections_still_open_after_the_timeout() {
+    struct SetOnDrop(Arc<AtomicBool>);
+
+    impl Drop for SetOnDrop {
+        fn drop(&mut self) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    let aborted = Arc::new(AtomicBool::new(false));
+    let router = Router::stateless().route(
+        "/forever",
+        get({
+            let aborted = aborted.clone();
+            move || {
+                let guard = SetOnDrop(aborted.clone());
+                async move {
+                    std::future::pending::<()>().await;
+                    drop(guard);
+                    "unreachable"
+                }
+            }
+        }),
+    );
+    let (addr, shutdown, server) = serve(router, Some(Duration::from_millis(100))).await;
+
+    let mut stream = send_get(addr, "/forever").await;
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    shutdown.send(()).unwrap();
+
+    tokio::time::timeout(Duration::from_secs(2), server)
+        .await
+        .expect("the shutdown timeout bounds the shutdown")
+        .unwrap();
+    assert!(aborted.load(Ordering::SeqCst));
+
+    let mut res = Vec::new();
+    assert_eq!(stream.read_to_end(&mut res).await.unwrap_or(0), 0);
+}
@@ -0,0 +1,144 @@
+#![cfg(feature = "tls")]
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use mini_axum::{Router, Service, method_router::get, tls::RustlsConfig};
+use rcgen::CertifiedKey;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_rustls::TlsConnector;
+use tokio_rustls::client::TlsStream;
+use tokio_rustls::rustls::pki_types::{CertificateDer, ServerName};
+use tokio_rustls::rustls::{ClientConfig, RootCertStore, crypto::ring};
+
+fn self_signed() -> CertifiedKey<rcgen::KeyPair> {
+    rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap()
+}
+
+fn pem_config(cert: &CertifiedKey<rcgen::KeyPair>) -> RustlsConfig {
+    RustlsConfig::from_pem(
+        cert.cert.pem().as_bytes(),
+        cert.signing_key.serialize_pem().as_bytes(),
+    )
+    .unwrap()
+}
+
+async fn serve(config: RustlsConfig) -> SocketAddr {
+    let tcp = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = tcp.local_addr().unwrap();
+
+    let rtr = Router::stateless().route("/", get(async || "Hello over TLS!"));
+    let svc = Service::tls(tcp, rtr, config);
+    tokio::spawn(async move {
+        let _ = svc.await;
+    });
+
+    addr
+}
+
+async fn connect(
+    addr: SocketAddr,
+    trusted: &CertifiedKey<rcgen::KeyPair>,
+    alpn: &[&[u8]],
+) -> std::io::Result<TlsStream<TcpStream>> {
+    let mut roots = RootCertStore::empty();
+    roots
+        .add(CertificateDer::from(trusted.cert.der().to_vec()))
+        .unwrap();
+
+    let mut config = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    config.alpn_protocols = alpn.iter().map(|proto| proto.to_vec()).collect();
+
+    let stream = TcpStream::connect(addr).await?;
+    TlsConnector::from(Arc::new(config))
+        .connect(ServerName::try_from("localhost").unwrap(), stream)
+        .await
+}
+
+async fn get_root(stream: &mut TlsStream<TcpStream>) -> String {
+    stream
+        .write_all(b"GET / HTTP/1.1\r\nhost: localhost\r\nconnection: close\r\n\r\n")
+        .await
+        .unwrap();
+
+    let mut res = String::new();
+    stream.read_to_string(&mut res).await.unwrap();
+    res
+}
+
+#[tokio::test]
+async fn serves_http1_over_tls() {
+    let cert = self_signed();
+    let addr = serve(pem_config(&cert)).await;
+
+    let mut stream = connect(addr, &cert, &[b"http/1.1"]).await.unwrap();
+    assert_eq!(stream.get_ref().1.alpn_protocol(), Some(&b"http/1.1"[..]));
+
+    let res = get_root(&mut stream).await;
+    assert!(res.starts_with("HTTP/1.1 200 OK"), "{res}");
+    assert!(res.ends_with("Hello over TLS!"), "{res}");
+}
+
+#[tokio::test]
+async fn advertises_h2_over_alpn() {
+    let cert = self_signed();
+    let addr = serve(pem_config(&cert)).await;
+
+    let stream = connect(addr, &cert, &[b"h2", b"http/1.1"]).await.unwrap();
+    assert_eq!(stream.get_ref().1.alpn_protocol(), Some(&b"h2"[..]));
+}
+
+#[tokio::test]
+async fn reloads_certificates_without_restarting() {
+    let old = self_signed();
+    let new = self_signed();
+    let config = pem_config(&old);
+    let addr = serve(config.clone()).await;
+
+    assert!(connect(addr, &new, &[b"http/1.1"]).await.is_err());
+
+    config
+        .reload_from_pem(
+            new.cert.pem().as_bytes(),
+            new.signing_key.serialize_pem().as_bytes(),
+        )
+        .unwrap();
+
+    let mut stream = connect(addr, &new, &[b"http/1.1"]).await.unwrap();
+    assert!(get_root(&mut stream).await.ends_with("Hello over TLS!"));
+    assert!(connect(addr, &old, &[b"http/1.1"]).await.is_err());
+}
+
+#[test]
+fn rejects_invalid_pem() {
+    assert!(RustlsConfig::from_pem(b"not a cert", b"not a key").is_err());
+}
+
+#[tokio::test]
+async fn shuts_down_while_a_handshake_is_pending() {
+    let tcp = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = tcp.local_addr().unwrap();
+    let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+
+    let svc = Service::tls(tcp, Router::stateless(), pem_config(&self_signed()))
+        .with_graceful_shutdown(async {
+            let _ = rx.await;
+        });
+    let server = tokio::spawn(async move { svc.await.unwrap() });
+
+    // Never sends a ClientHello.
+    let _silent = TcpStream::connect(addr).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    tx.send(()).unwrap();
+
+    tokio::time::timeout(Duration::from_secs(2), server)
+        .await
+        .expect("pending handshakes don't hold up the shutdown")
+        .unwrap();
+}
@@ -0,0 +1,93 @@
+use std::net::SocketAddr;
+
+use futures::{SinkExt, StreamExt};
+use mini_axum::{
+    Router, Service,
+    method_router::get,
+    response::IntoMiniResponse,
+    ws::{Message, WebSocketUpgrade},
+};
+use tokio::net::TcpListener;
+use tokio_tungstenite::tungstenite::{self, client::IntoClientRequest, http::StatusCode};
+
+async fn echo(ws: WebSocketUpgrade) -> impl IntoMiniResponse {
+    ws.protocols(["echo"]).on_upgrade(|mut socket| async move {
+        while let Some(Ok(msg)) = socket.recv().await {
+            let reply = match msg {
+                Message::Text(text) => Message::Text(format!("echo: {text}")),
+                Message::Binary(bytes) => Message::Binary(bytes),
+                Message::Close(_) => break,
+                _ => continue,
+            };
+
+            if socket.send(reply).await.is_err() {
+                break;
+            }
+        }
+    })
+}
+
+async fn serve() -> SocketAddr {
+    let tcp = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = tcp.local_addr().unwrap();
+
+    let rtr = Router::stateless().route("/ws", get(echo));
+    let svc = Service::new(tcp, rtr);
+    tokio::spawn(async move {
+        let _ = svc.await;
+    });
+
+    addr
+}
+
+#[tokio::test]
+async fn echoes_text_and_binary_messages() {
+    let addr = serve().await;
+
+    let mut req = format!("ws://{addr}/ws").into_client_request().unwrap();
+    req.headers_mut()
+        .insert("sec-websocket-protocol", "chat, echo".parse().unwrap());
+
+    let (mut socket, res) = tokio_tungstenite::connect_async(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::SWITCHING_PROTOCOLS);
+    assert_eq!(res.headers()["sec-websocket-protocol"], "echo");
+
+    socket
+        .send(tungstenite::Message::text("hello"))
+        .await
+        .unwrap();
+    let reply = socket.next().await.unwrap().unwrap();
+    assert_eq!(reply, tungstenite::Message::text("echo: hello"));
+
+    socket
+        .send(tungstenite::Message::binary(vec![1, 2, 3]))
+        .await
+        .unwrap();
+    let reply = socket.next().await.unwrap().unwrap();
+    assert_eq!(reply, tungstenite::Message::binary(vec![1, 2, 3]));
+
+    socket.close(None).await.unwrap();
+    while let Some(Ok(_)) = socket.next().await {}
+}
+
+#[tokio::test]
+async fn rejects_plain_http_requests() {
+    let addr = serve().await;
+
+    let err = tokio_tungstenite::connect_async(format!("ws://{addr}/nope"))
+        .await
+        .unwrap_err();
+    let tungstenite::Error::Http(res) = err else {
+        panic!("expected an HTTP error, got {err:?}");
+    };
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    let mut req = format!("ws://{addr}/ws").into_client_request().unwrap();
+    req.headers_mut()
+        .insert("sec-websocket-version", "8".parse().unwrap());
+    let err = tokio_tungstenite::connect_async(req).await.unwrap_err();
+    let tungstenite::Error::Http(res) = err else {
+        panic!("expected an HTTP error, got {err:?}");
+    };
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}